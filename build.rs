@@ -0,0 +1,25 @@
+// Generates Rust bindings for the shader globals, see src/bindgen.rs.
+// Reruns whenever a shader changes, so the bindings can't drift from gorilla.slang.
+
+use std::{env, fs, path::Path};
+
+#[path = "src/bindgen.rs"]
+mod bindgen;
+#[path = "src/diagnostics.rs"]
+mod diagnostics;
+
+fn main() {
+    println!("cargo:rerun-if-changed=src/shader");
+    println!("cargo:rerun-if-changed=src/bindgen.rs");
+    println!("cargo:rerun-if-changed=src/diagnostics.rs");
+
+    let bindings = bindgen::generate_bindings(&bindgen::BindgenConfig {
+        search_path: "src/shader",
+        module: "gorilla.slang",
+        entry_points: &["vertex", "fragment"],
+    })
+    .unwrap_or_else(|e| panic!("Failed to generate bindings for gorilla.slang:\n{}", e));
+
+    let out_dir = env::var("OUT_DIR").unwrap();
+    fs::write(Path::new(&out_dir).join("gorilla_bindings.rs"), bindings).unwrap();
+}
@@ -1,96 +1,670 @@
-use proc_macro2::{Span, TokenStream};
+use proc_macro2::{Literal, TokenStream};
 use quote::{format_ident, quote};
-use syn::{Data, DeriveInput, Fields, parse_macro_input, punctuated::Punctuated, token::Comma};
+use syn::{
+    Attribute, Data, DataEnum, DeriveInput, Expr, Field, Fields, Ident, Index, LitStr, Path,
+    parse_macro_input, parse_quote,
+};
 
-#[proc_macro_derive(Writable)]
-pub fn derive_writable(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
-    let input = parse_macro_input!(input as DeriveInput);
+// Options on the type itself from #[writable(...)]
+struct ContainerOptions {
+    // Slang field holding the variant of a data-carrying enum
+    tag: String,
+    // Path to wgpu_tidy, for crates that only see it through a re-export
+    krate: Path,
+}
 
-    // Extract struct name
-    let name = &input.ident;
+fn container_options(attrs: &[Attribute]) -> syn::Result<ContainerOptions> {
+    let mut options = ContainerOptions {
+        tag: "tag".into(),
+        krate: parse_quote!(::wgpu_tidy),
+    };
+    for attr in attrs.iter().filter(|a| a.path().is_ident("writable")) {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("tag") {
+                options.tag = meta.value()?.parse::<LitStr>()?.value();
+                Ok(())
+            } else if meta.path.is_ident("crate") {
+                options.krate = meta.value()?.parse()?;
+                Ok(())
+            } else {
+                Err(meta.error("expected `tag = \"...\"` or `crate = path`"))
+            }
+        })?;
+    }
+    Ok(options)
+}
 
-    // Prepare implementation based on struct fields
-    let write_implementation = match &input.data {
-        Data::Struct(data_struct) => match &data_struct.fields {
-            Fields::Named(fields) => {
-                let field_writes = fields
-                    .named
-                    .iter()
-                    .enumerate()
-                    .map(|(index, field)| {
-                        let field_name = &field.ident;
-                        let field_ty = &field.ty;
-                        let index_u32 = index as u32;
-
-                        quote! {
-                            let cursor = c.navigate_field(#index_u32).unwrap();
-                            self.#field_name.write_at_cursor(cursor, device, queue, binding_resources)
-                        }
-                    })
-                    .collect::<Vec<_>>();
-
-                quote! {
-                    fn write_at_cursor(
-                        &self,
-                        c: reflection::Cursor,
-                        device: &wgpu::Device,
-                        queue: &wgpu::Queue,
-                        binding_resources: &mut reflection::BindingResources,
-                    ) {
-                        #(#field_writes;)*
-                    }
+// Per-field options from #[writable(...)]
+#[derive(Default)]
+struct FieldOptions {
+    skip: bool,
+    name: Option<String>,
+    with: Option<Path>,
+}
+
+fn field_options(field: &Field) -> syn::Result<FieldOptions> {
+    let mut options = FieldOptions::default();
+    for attr in field.attrs.iter().filter(|a| a.path().is_ident("writable")) {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("skip") {
+                options.skip = true;
+                Ok(())
+            } else if meta.path.is_ident("name") {
+                options.name = Some(meta.value()?.parse::<LitStr>()?.value());
+                Ok(())
+            } else if meta.path.is_ident("with") {
+                options.with = Some(meta.value()?.parse()?);
+                Ok(())
+            } else {
+                Err(meta.error("expected `skip`, `name = \"...\"` or `with = path`"))
+            }
+        })?;
+    }
+    Ok(options)
+}
+
+// `cursor` is in scope when `access` is written. `access` is a reference to the field.
+fn write_field(krate: &Path, access: TokenStream, options: &FieldOptions) -> TokenStream {
+    match &options.with {
+        Some(with) => quote! {
+            #with(#access, cursor, device, queue, binding_resources)
+        },
+        None => quote! {
+            #krate::reflection::Writable::write_at_cursor(
+                #access,
+                cursor,
+                device,
+                queue,
+                binding_resources,
+            )
+        },
+    }
+}
+
+// Cursor for the Slang field `slang_name`. Panics naming both sides if reflection has no
+// such field, since the Rust type and the shader disagree. `owner` is the Rust type or variant.
+fn navigate_by_name(owner: &str, rust_field: &str, slang_name: &str) -> TokenStream {
+    quote! {
+        c.navigate_field_by_name(#slang_name).unwrap_or_else(|| {
+            ::std::panic!(
+                "{}::{} is written to Slang field `{}`, but Slang type `{}` has no such field",
+                #owner,
+                #rust_field,
+                #slang_name,
+                c.type_layout().name().unwrap_or("<anonymous>"),
+            )
+        })
+    }
+}
+
+// `access` gives a reference to the field with the given index.
+// Unnamed fields start at Slang field `first_index`.
+fn field_writes(
+    krate: &Path,
+    owner: &str,
+    fields: &Fields,
+    access: impl Fn(usize, &Field) -> TokenStream,
+    first_index: u32,
+) -> syn::Result<Vec<TokenStream>> {
+    let mut writes = Vec::new();
+    match fields {
+        // Named fields are looked up by name, so Rust and Slang field order can differ
+        Fields::Named(fields) => {
+            for (index, field) in fields.named.iter().enumerate() {
+                let options = field_options(field)?;
+                if options.skip {
+                    continue;
                 }
+                let field_name = field.ident.as_ref().unwrap().to_string();
+                let slang_name = options.name.clone().unwrap_or(field_name.clone());
+                let cursor = navigate_by_name(owner, &field_name, &slang_name);
+                let write = write_field(krate, access(index, field), &options);
+                writes.push(quote! {
+                    let cursor = #cursor;
+                    #write
+                });
             }
-            Fields::Unnamed(fields) => {
-                let field_writes = fields
-                    .unnamed
-                    .iter()
-                    .enumerate()
-                    .map(|(index, field)| {
-                        let field_ty = &field.ty;
-                        let field_ident = format_ident!("_{}", index);
-                        let index_u32 = index as u32;
-
-                        quote! {
-                            let cursor = c.navigate_field(#index_u32).unwrap();
-                            self.#field_ident.write_at_cursor(cursor, device, queue, binding_resources)
-                        }
-                    })
-                    .collect::<Vec<_>>();
-
-                quote! {
-                    fn write_at_cursor(
-                        &self,
-                        c: reflection::Cursor,
-                        device: &wgpu::Device,
-                        queue: &wgpu::Queue,
-                        binding_resources: &mut reflection::BindingResources,
-                    ) {
-                        #(#field_writes;)*
-                    }
+        }
+        // Unnamed fields are matched by position, not counting skipped ones
+        Fields::Unnamed(fields) => {
+            let mut slang_index = first_index;
+            for (index, field) in fields.unnamed.iter().enumerate() {
+                let options = field_options(field)?;
+                if options.skip {
+                    continue;
                 }
+                let cursor = match &options.name {
+                    Some(slang_name) => navigate_by_name(owner, &index.to_string(), slang_name),
+                    None => quote!(c.navigate_field(#slang_index).unwrap()),
+                };
+                let write = write_field(krate, access(index, field), &options);
+                writes.push(quote! {
+                    let cursor = #cursor;
+                    #write
+                });
+                slang_index += 1;
+            }
+        }
+        Fields::Unit => {}
+    }
+    Ok(writes)
+}
+
+// Discriminant of every variant as an i32 expression, following Rust's
+// rule that an implicit discriminant is one more than the previous one.
+fn discriminants(data: &DataEnum) -> Vec<TokenStream> {
+    let mut base: Option<&Expr> = None;
+    let mut offset = 0i32;
+    data.variants
+        .iter()
+        .map(|variant| {
+            if let Some((_, expr)) = &variant.discriminant {
+                base = Some(expr);
+                offset = 0;
             }
-            Fields::Unit => {
-                quote! {
-                    fn write_at_cursor(
-                        &self,
-                        c: reflection::Cursor,
-                        device: &wgpu::Device,
-                        queue: &wgpu::Queue,
-                        binding_resources: &mut reflection::BindingResources,
-                    ) {
+            let k = Literal::i32_unsuffixed(offset);
+            offset += 1;
+            match base {
+                Some(expr) => quote!(((#expr) as i32) + #k),
+                None => quote!(#k),
+            }
+        })
+        .collect()
+}
+
+// Fieldless enums are written as their discriminant, like a Slang enum or int.
+// Anything else is written to a Slang struct: the discriminant goes to the tag field
+// and the active variant's fields go to the fields with matching names, or for
+// tuple variants to the fields right after the tag.
+fn enum_writes(
+    name: &Ident,
+    data: &DataEnum,
+    options: &ContainerOptions,
+) -> syn::Result<TokenStream> {
+    let krate = &options.krate;
+    // There is no value to write, and `match self {}` on a reference wouldn't compile
+    if data.variants.is_empty() {
+        return Err(syn::Error::new_spanned(
+            name,
+            "Writable cannot be derived for enums without variants",
+        ));
+    }
+    let discriminants = discriminants(data);
+    if data.variants.iter().all(|v| v.fields.is_empty()) {
+        let variants = data.variants.iter().map(|v| &v.ident);
+        return Ok(quote! {
+            let tag: i32 = match self {
+                #(Self::#variants => #discriminants,)*
+            };
+            #krate::reflection::Writable::write_at_cursor(
+                &tag,
+                c,
+                device,
+                queue,
+                binding_resources,
+            );
+        });
+    }
+    let tag_name = &options.tag;
+    let mut arms = Vec::new();
+    for (variant, discriminant) in data.variants.iter().zip(&discriminants) {
+        let variant_name = &variant.ident;
+        let owner = format!("{}::{}", name, variant_name);
+        let binding = |index: usize, field: &Field| match &field.ident {
+            Some(ident) => format_ident!("__field_{}", ident),
+            None => format_ident!("__field{}", index),
+        };
+        let bindings: Vec<_> = variant
+            .fields
+            .iter()
+            .enumerate()
+            .map(|(index, field)| binding(index, field))
+            .collect();
+        let pattern = match &variant.fields {
+            Fields::Named(fields) => {
+                let names = fields.named.iter().map(|f| &f.ident);
+                quote!(Self::#variant_name { #(#names: #bindings),* })
+            }
+            Fields::Unnamed(_) => quote!(Self::#variant_name(#(#bindings),*)),
+            Fields::Unit => quote!(Self::#variant_name),
+        };
+        let writes = field_writes(
+            krate,
+            &owner,
+            &variant.fields,
+            |index, field| {
+                let b = binding(index, field);
+                quote!(#b)
+            },
+            1,
+        )?;
+        let tag_cursor = navigate_by_name(&owner, "<tag>", tag_name);
+        arms.push(quote! {
+            #pattern => {
+                let tag: i32 = #discriminant;
+                let cursor = #tag_cursor;
+                #krate::reflection::Writable::write_at_cursor(
+                    &tag,
+                    cursor,
+                    device,
+                    queue,
+                    binding_resources,
+                );
+                #(#writes;)*
+            }
+        });
+    }
+    Ok(quote! {
+        #[allow(unused_variables)]
+        match self {
+            #(#arms)*
+        }
+    })
+}
+
+fn writable_impl(input: &DeriveInput) -> syn::Result<TokenStream> {
+    let name = &input.ident;
+    let options = container_options(&input.attrs)?;
+
+    let krate = &options.krate;
+
+    let body = match &input.data {
+        Data::Struct(data_struct) => {
+            let writes = field_writes(
+                krate,
+                &name.to_string(),
+                &data_struct.fields,
+                |index, field| match &field.ident {
+                    Some(ident) => quote!(&self.#ident),
+                    None => {
+                        let index = Index::from(index);
+                        quote!(&self.#index)
                     }
-                }
+                },
+                0,
+            )?;
+            quote!(#(#writes;)*)
+        }
+        Data::Enum(data_enum) => enum_writes(name, data_enum, &options)?,
+        Data::Union(_) => {
+            return Err(syn::Error::new_spanned(
+                &input.ident,
+                "Writable cannot be derived for unions",
+            ));
+        }
+    };
+
+    // Bound the types of the fields that actually get written, so type parameters
+    // that only show up in skipped or `with` fields don't have to be Writable
+    let mut generics = input.generics.clone();
+    if generics.type_params().next().is_some() {
+        let fields: Vec<&Field> = match &input.data {
+            Data::Struct(data_struct) => data_struct.fields.iter().collect(),
+            Data::Enum(data_enum) => data_enum.variants.iter().flat_map(|v| &v.fields).collect(),
+            Data::Union(_) => vec![],
+        };
+        let where_clause = generics.make_where_clause();
+        for field in fields {
+            let options = field_options(field)?;
+            if !options.skip && options.with.is_none() {
+                let ty = &field.ty;
+                where_clause
+                    .predicates
+                    .push(parse_quote!(#ty: #krate::reflection::Writable));
             }
-        },
-        _ => panic!("Writable can only be derived for structs"),
+        }
+    }
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+
+    Ok(quote! {
+        impl #impl_generics #krate::reflection::Writable for #name #ty_generics #where_clause {
+            fn write_at_cursor(
+                &self,
+                c: #krate::reflection::Cursor,
+                device: &#krate::wgpu::Device,
+                queue: &#krate::wgpu::Queue,
+                binding_resources: &mut #krate::reflection::BindingResources,
+            ) {
+                #body
+            }
+        }
+    })
+}
+
+#[proc_macro_derive(Writable, attributes(writable))]
+pub fn derive_writable(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    match writable_impl(&input) {
+        Ok(expanded) => expanded.into(),
+        Err(e) => e.to_compile_error().into(),
+    }
+}
+
+// Options on the type itself from #[slang(...)]
+struct SlangOptions {
+    name: Option<String>,
+    // Interfaces the Slang struct conforms to, e.g. IDifferentiable
+    implements: Vec<String>,
+    krate: Path,
+}
+
+fn slang_options(attrs: &[Attribute]) -> syn::Result<SlangOptions> {
+    let mut options = SlangOptions {
+        name: None,
+        implements: Vec::new(),
+        krate: parse_quote!(::wgpu_tidy),
     };
+    for attr in attrs.iter().filter(|a| a.path().is_ident("slang")) {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("name") {
+                options.name = Some(meta.value()?.parse::<LitStr>()?.value());
+                Ok(())
+            } else if meta.path.is_ident("implements") {
+                let interfaces = meta.value()?.parse::<LitStr>()?.value();
+                options
+                    .implements
+                    .extend(interfaces.split(',').map(|i| i.trim().to_string()));
+                Ok(())
+            } else if meta.path.is_ident("crate") {
+                options.krate = meta.value()?.parse()?;
+                Ok(())
+            } else {
+                Err(meta
+                    .error("expected `name = \"...\"`, `implements = \"...\"` or `crate = path`"))
+            }
+        })?;
+    }
+    Ok(options)
+}
+
+// Per-field options from #[slang(...)]
+#[derive(Default)]
+struct SlangFieldOptions {
+    skip: bool,
+    name: Option<String>,
+}
 
-    let expanded = quote! {
-        impl Writable for #name {
-            #write_implementation
+fn slang_field_options(field: &Field) -> syn::Result<SlangFieldOptions> {
+    let mut options = SlangFieldOptions::default();
+    for attr in field.attrs.iter().filter(|a| a.path().is_ident("slang")) {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("skip") {
+                options.skip = true;
+                Ok(())
+            } else if meta.path.is_ident("name") {
+                options.name = Some(meta.value()?.parse::<LitStr>()?.value());
+                Ok(())
+            } else {
+                Err(meta.error("expected `skip` or `name = \"...\"`"))
+            }
+        })?;
+    }
+    Ok(options)
+}
+
+fn slang_type_impl(input: &DeriveInput) -> syn::Result<TokenStream> {
+    let name = &input.ident;
+    let options = slang_options(&input.attrs)?;
+    let krate = &options.krate;
+
+    if input.generics.type_params().next().is_some() {
+        return Err(syn::Error::new_spanned(
+            &input.generics,
+            "SlangType cannot be derived for generic types",
+        ));
+    }
+    let fields = match &input.data {
+        Data::Struct(data_struct) => match &data_struct.fields {
+            Fields::Named(fields) => fields.named.iter().collect(),
+            Fields::Unit => vec![],
+            Fields::Unnamed(_) => {
+                return Err(syn::Error::new_spanned(
+                    name,
+                    "SlangType needs named fields, Slang structs have no tuple form",
+                ));
+            }
+        },
+        _ => {
+            return Err(syn::Error::new_spanned(
+                name,
+                "SlangType can only be derived for structs",
+            ));
         }
     };
 
-    proc_macro::TokenStream::from(expanded)
+    let slang_name = options.name.clone().unwrap_or(name.to_string());
+    let header = if options.implements.is_empty() {
+        format!("struct {}\n{{\n", slang_name)
+    } else {
+        format!(
+            "struct {} : {}\n{{\n",
+            slang_name,
+            options.implements.join(", ")
+        )
+    };
+
+    let mut dependencies = Vec::new();
+    let mut lines = Vec::new();
+    for field in fields {
+        let field_options = slang_field_options(field)?;
+        if field_options.skip {
+            continue;
+        }
+        let ty = &field.ty;
+        let field_name = field_options
+            .name
+            .unwrap_or(field.ident.as_ref().unwrap().to_string());
+        dependencies.push(quote! {
+            <#ty as #krate::codegen::SlangType>::declare(module);
+        });
+        lines.push(quote! {
+            source.push_str(&::std::format!(
+                "    {} {};\n",
+                <#ty as #krate::codegen::SlangType>::slang_name(),
+                #field_name,
+            ));
+        });
+    }
+
+    Ok(quote! {
+        impl #krate::codegen::SlangType for #name {
+            fn slang_name() -> ::std::string::String {
+                ::std::string::String::from(#slang_name)
+            }
+
+            fn declare(module: &mut #krate::codegen::SlangModule) {
+                if module.contains(#slang_name) {
+                    return;
+                }
+                #(#dependencies)*
+                let mut source = ::std::string::String::from(#header);
+                #(#lines)*
+                source.push_str("}\n");
+                module.push_declaration(::std::string::String::from(#slang_name), source);
+            }
+        }
+    })
+}
+
+#[proc_macro_derive(SlangType, attributes(slang))]
+pub fn derive_slang_type(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    match slang_type_impl(&input) {
+        Ok(expanded) => expanded.into(),
+        Err(e) => e.to_compile_error().into(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn expand_writable(input: DeriveInput) -> String {
+        writable_impl(&input).unwrap().to_string()
+    }
+
+    #[test]
+    fn named_fields_are_looked_up_by_name() {
+        let out = expand_writable(parse_quote! {
+            struct Params {
+                support: f32,
+                #[writable(name = "count")]
+                point_count: i32,
+            }
+        });
+        assert!(out.contains("navigate_field_by_name (\"support\")"));
+        assert!(out.contains("navigate_field_by_name (\"count\")"));
+        assert!(!out.contains("\"point_count\")"));
+        assert!(!out.contains("navigate_field ("));
+    }
+
+    #[test]
+    fn missing_fields_name_both_sides() {
+        let out = expand_writable(parse_quote! {
+            struct Params {
+                #[writable(name = "count")]
+                point_count: i32,
+            }
+        });
+        assert!(out.contains("\"Params\""));
+        assert!(out.contains("\"point_count\""));
+        assert!(out.contains("c . type_layout () . name ()"));
+        assert!(!out.contains("expect"));
+    }
+
+    #[test]
+    fn skipped_fields_are_not_written() {
+        let out = expand_writable(parse_quote! {
+            struct Params {
+                support: f32,
+                #[writable(skip)]
+                written_already: bool,
+            }
+        });
+        assert!(!out.contains("written_already"));
+    }
+
+    #[test]
+    fn with_replaces_the_write() {
+        let out = expand_writable(parse_quote! {
+            struct Params {
+                #[writable(with = write_points)]
+                points: Vec<f32>,
+            }
+        });
+        assert!(out.contains(
+            "write_points (& self . points , cursor , device , queue , binding_resources)"
+        ));
+        assert!(!out.contains("Writable :: write_at_cursor"));
+    }
+
+    #[test]
+    fn tuple_fields_go_by_position_without_skipped_ones() {
+        let out = expand_writable(parse_quote! {
+            struct Pair(#[writable(skip)] u8, f32, i32);
+        });
+        assert!(out.contains("& self . 1"));
+        assert!(out.contains("& self . 2"));
+        assert!(!out.contains("& self . 0"));
+        assert!(out.contains("navigate_field (0u32)"));
+        assert!(out.contains("navigate_field (1u32)"));
+    }
+
+    #[test]
+    fn generics_are_bounded_by_their_written_fields() {
+        let out = expand_writable(parse_quote! {
+            struct Wrapper<T, U> {
+                value: T,
+                #[writable(skip)]
+                extra: U,
+            }
+        });
+        assert!(out.contains("where T : :: wgpu_tidy :: reflection :: Writable"));
+        assert!(!out.contains("U : :: wgpu_tidy"));
+    }
+
+    #[test]
+    fn fieldless_enums_are_written_as_discriminants() {
+        let out = expand_writable(parse_quote! {
+            enum Mode {
+                A,
+                B = 5,
+                C,
+            }
+        });
+        assert!(out.contains("Self :: A => 0"));
+        assert!(out.contains("Self :: B => ((5) as i32) + 0"));
+        assert!(out.contains("Self :: C => ((5) as i32) + 1"));
+        assert!(!out.contains("navigate_field_by_name"));
+    }
+
+    #[test]
+    fn data_enums_write_a_tag_and_the_payload() {
+        let out = expand_writable(parse_quote! {
+            #[writable(tag = "kind")]
+            enum Shape {
+                Circle { radius: f32 },
+                Point,
+            }
+        });
+        assert!(out.contains("navigate_field_by_name (\"kind\")"));
+        assert!(out.contains("navigate_field_by_name (\"radius\")"));
+        assert!(out.contains("\"Shape::Circle\""));
+    }
+
+    #[test]
+    fn paths_are_fully_qualified() {
+        let out = expand_writable(parse_quote! {
+            struct Params {
+                support: f32,
+            }
+        });
+        assert!(out.contains("impl :: wgpu_tidy :: reflection :: Writable for Params"));
+        assert!(out.contains("c : :: wgpu_tidy :: reflection :: Cursor"));
+        assert!(out.contains("device : & :: wgpu_tidy :: wgpu :: Device"));
+    }
+
+    #[test]
+    fn crate_can_be_overridden() {
+        let out = expand_writable(parse_quote! {
+            #[writable(crate = my_engine::tidy)]
+            struct Params {
+                support: f32,
+            }
+        });
+        assert!(out.contains("impl my_engine :: tidy :: reflection :: Writable for Params"));
+        assert!(!out.contains("wgpu_tidy"));
+    }
+
+    #[test]
+    fn unions_are_rejected() {
+        let input: DeriveInput = parse_quote! {
+            union Bits {
+                f: f32,
+                u: u32,
+            }
+        };
+        assert!(writable_impl(&input).is_err());
+    }
+
+    #[test]
+    fn empty_enums_are_rejected() {
+        let input: DeriveInput = parse_quote! {
+            enum Empty {}
+        };
+        let e = writable_impl(&input).unwrap_err();
+        assert!(e.to_string().contains("without variants"));
+    }
+
+    #[test]
+    fn unknown_field_options_are_errors() {
+        let input: DeriveInput = parse_quote! {
+            struct Params {
+                #[writable(rename = "x")]
+                x: f32,
+            }
+        };
+        let e = writable_impl(&input).unwrap_err();
+        assert!(e.to_string().contains("expected `skip`"));
+    }
 }
@@ -0,0 +1,891 @@
+// Rust bindings generated from Slang reflection.
+// For every struct that ends up in a uniform or storage buffer we emit a #[repr(C)] struct
+// with explicit padding fields and offset/size asserts, and for every bind group a struct
+// with one typed field per binding that builds the wgpu bind group and its layout.
+// The binding numbers follow `layout_entries_wowee`: sequential within a set, in field order.
+// This file is also compiled into build.rs, so it may only depend on slang, std
+// and diagnostics.rs, and can't share reflection.rs's wgpu-typed helpers. A test in lib.rs
+// checks that the generated layout entries match `layout_entries_wowee` for gorilla.slang.
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    ffi::CString,
+    fmt::Write,
+};
+
+use slang::{
+    Downcast, ImageFormat, ParameterCategory, ResourceAccess, ResourceShape, ScalarType, TypeKind,
+    reflection::{Type, TypeLayout, VariableLayout},
+};
+
+use crate::diagnostics::{error_log, parse_diagnostics};
+
+pub struct BindgenConfig<'a> {
+    pub search_path: &'a str,
+    pub module: &'a str,
+    pub entry_points: &'a [&'a str],
+}
+
+// Compiles the module the same way the app does and generates bindings for its globals
+pub fn generate_bindings(config: &BindgenConfig) -> Result<String, String> {
+    let global_session = slang::GlobalSession::new().ok_or("could not create a Slang session")?;
+    let search_path = CString::new(config.search_path).map_err(|e| e.to_string())?;
+
+    let session_options = slang::CompilerOptions::default().matrix_layout_row(true);
+
+    let target_desc = slang::TargetDesc::default()
+        .format(slang::CompileTarget::Wgsl)
+        .profile(global_session.find_profile("glsl_450"));
+
+    let targets = [target_desc];
+    let search_paths = [search_path.as_ptr()];
+
+    let session_desc = slang::SessionDesc::default()
+        .targets(&targets)
+        .search_paths(&search_paths)
+        .options(&session_options);
+
+    let session = global_session
+        .create_session(&session_desc)
+        .ok_or("could not create a Slang session")?;
+    let module = session.load_module(config.module).map_err(describe_error)?;
+
+    let mut components = vec![module.downcast().clone()];
+    for name in config.entry_points {
+        let entry_point = module
+            .find_entry_point_by_name(name)
+            .ok_or(format!("no entry point `{}` in {}", name, config.module))?;
+        components.push(entry_point.downcast().clone());
+    }
+    let program = session
+        .create_composite_component_type(&components)
+        .map_err(describe_error)?;
+    let linked_program = program.link().map_err(describe_error)?;
+    let reflection = linked_program.layout(0).map_err(describe_error)?;
+
+    bindings_from_layout(reflection.global_params_type_layout())
+}
+
+fn describe_error(e: slang::Error) -> String {
+    match error_log(&e) {
+        Some(log) => {
+            let diagnostics = parse_diagnostics(&log);
+            if diagnostics.is_empty() {
+                log
+            } else {
+                let lines: Vec<String> = diagnostics.iter().map(|d| d.to_string()).collect();
+                lines.join("\n")
+            }
+        }
+        None => format!("{:?}", e),
+    }
+}
+
+// Fails on globals the app can't bind, naming the variable
+pub fn bindings_from_layout(global_tl: &TypeLayout) -> Result<String, String> {
+    let mut generator = Generator::default();
+    let mut bindings = Vec::new();
+    let mut next_set = 1;
+    collect_bindings(
+        &mut generator,
+        global_tl,
+        "",
+        0,
+        false,
+        &mut next_set,
+        &mut bindings,
+    )?;
+
+    let mut out = String::from("// Generated from Slang reflection by wgpu_tidy. Do not edit.\n");
+    out.push_str(PADDED_SOURCE);
+    for source in &generator.sources {
+        out.push('\n');
+        out.push_str(source);
+    }
+
+    let mut sets: BTreeMap<usize, Vec<Binding>> = BTreeMap::new();
+    for binding in bindings {
+        sets.entry(binding.set).or_default().push(binding);
+    }
+    for (set, bindings) in &sets {
+        out.push('\n');
+        out.push_str(&bind_group_source(*set, bindings));
+    }
+
+    // The same shape ShaderProgram::layout_entries has
+    out.push_str("\npub fn layout_entries() -> std::collections::HashMap<usize, Vec<wgpu::BindGroupLayoutEntry>> {\n");
+    out.push_str("    std::collections::HashMap::from([\n");
+    for set in sets.keys() {
+        writeln!(
+            out,
+            "        ({}, BindGroup{}::LAYOUT_ENTRIES.to_vec()),",
+            set, set
+        )
+        .unwrap();
+    }
+    out.push_str("    ])\n}\n");
+    Ok(out)
+}
+
+// Array elements whose stride is larger than the element itself
+const PADDED_SOURCE: &str = r#"
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Padded<T, const P: usize> {
+    pub value: T,
+    pub _pad: [u8; P],
+}
+unsafe impl<T: bytemuck::Zeroable, const P: usize> bytemuck::Zeroable for Padded<T, P> {}
+unsafe impl<T: bytemuck::Pod, const P: usize> bytemuck::Pod for Padded<T, P> {}
+"#;
+
+enum BindingKind {
+    Uniform {
+        size: usize,
+        ty: String,
+    },
+    Storage {
+        read_only: bool,
+        // Of one element
+        size: usize,
+        ty: Option<String>,
+    },
+    // Variant names of wgpu's enums, spelled out in the generated code
+    Texture {
+        view_dimension: &'static str,
+        sample_type: &'static str,
+    },
+    StorageTexture {
+        view_dimension: &'static str,
+        access: &'static str,
+        format: &'static str,
+    },
+    Sampler {
+        binding_type: &'static str,
+    },
+}
+
+struct Binding {
+    set: usize,
+    binding: u32,
+    name: String,
+    kind: BindingKind,
+}
+
+fn push_binding(bindings: &mut Vec<Binding>, set: usize, name: String, kind: BindingKind) {
+    let binding = bindings.iter().filter(|b| b.set == set).count() as u32;
+    bindings.push(Binding {
+        set,
+        binding,
+        name,
+        kind,
+    });
+}
+
+// `in_buffer` is set inside constant buffers and parameter blocks, where plain data fields are
+// part of the buffer. Anywhere else they would be loose uniforms, which the app can't bind.
+fn collect_bindings(
+    generator: &mut Generator,
+    tl: &TypeLayout,
+    prefix: &str,
+    set: usize,
+    in_buffer: bool,
+    next_set: &mut usize,
+    bindings: &mut Vec<Binding>,
+) -> Result<(), String> {
+    for i in 0..tl.field_count() {
+        let vl = tl.field_by_index(i).unwrap();
+        let field_name = vl.variable().map_or("unnamed", |v| v.name());
+        let name = if prefix.is_empty() {
+            field_name.to_string()
+        } else {
+            format!("{}_{}", prefix, field_name)
+        };
+        collect_binding(generator, vl, &name, set, in_buffer, next_set, bindings)?;
+    }
+    Ok(())
+}
+
+fn collect_binding(
+    generator: &mut Generator,
+    vl: &VariableLayout,
+    name: &str,
+    set: usize,
+    in_buffer: bool,
+    next_set: &mut usize,
+    bindings: &mut Vec<Binding>,
+) -> Result<(), String> {
+    let tl = vl.type_layout();
+    match tl.kind() {
+        TypeKind::Struct => {
+            collect_bindings(generator, tl, name, set, in_buffer, next_set, bindings)?;
+        }
+        TypeKind::ConstantBuffer | TypeKind::ParameterBlock => {
+            let set = if tl.kind() == TypeKind::ParameterBlock {
+                *next_set += 1;
+                *next_set - 1
+            } else {
+                set
+            };
+            let element_tl = tl.element_type_layout();
+            if tl
+                .container_var_layout()
+                .type_layout()
+                .size(ParameterCategory::DescriptorTableSlot)
+                > 0
+            {
+                let size = element_tl.size(ParameterCategory::Uniform);
+                let (ty, _) = generator.rust_type(element_tl).ok_or_else(|| {
+                    format!(
+                        "`{}` holds a {}, which has no Rust type",
+                        name,
+                        element_tl.name().unwrap_or("<anon_type>")
+                    )
+                })?;
+                push_binding(
+                    bindings,
+                    set,
+                    name.to_string(),
+                    BindingKind::Uniform { size, ty },
+                );
+            }
+            collect_binding(
+                generator,
+                tl.element_var_layout(),
+                name,
+                set,
+                true,
+                next_set,
+                bindings,
+            )?;
+        }
+        TypeKind::SamplerState => {
+            let binding_type = match tl.ty().and_then(|ty| ty.name()) {
+                Some("SamplerComparisonState") => "Comparison",
+                _ => "Filtering",
+            };
+            push_binding(
+                bindings,
+                set,
+                name.to_string(),
+                BindingKind::Sampler { binding_type },
+            );
+        }
+        // The format is an attribute of the variable, for storage textures
+        TypeKind::Resource => {
+            collect_resource(generator, tl, vl.image_format(), name, set, bindings)?;
+        }
+        TypeKind::Scalar | TypeKind::Vector | TypeKind::Matrix | TypeKind::Array
+            if in_buffer && tl.size(ParameterCategory::DescriptorTableSlot) == 0 => {}
+        kind => {
+            return Err(format!(
+                "`{}` is a {:?}, which has no binding; loose uniforms have to go in a ConstantBuffer",
+                name, kind
+            ));
+        }
+    }
+    Ok(())
+}
+
+fn collect_resource(
+    generator: &mut Generator,
+    tl: &TypeLayout,
+    image_format: ImageFormat,
+    name: &str,
+    set: usize,
+    bindings: &mut Vec<Binding>,
+) -> Result<(), String> {
+    let ty = tl
+        .ty()
+        .ok_or_else(|| format!("`{}` has a resource type Slang can't reflect", name))?;
+    let type_name = ty.name().unwrap_or("<anon_type>");
+    let access = ty.resource_access();
+    if matches!(
+        ty.resource_shape(),
+        ResourceShape::SlangStructuredBuffer | ResourceShape::SlangByteAddressBuffer
+    ) {
+        let element_tl = tl.element_type_layout();
+        push_binding(
+            bindings,
+            set,
+            name.to_string(),
+            BindingKind::Storage {
+                read_only: access == ResourceAccess::Read,
+                size: element_tl.size(ParameterCategory::Uniform),
+                ty: generator.rust_type(element_tl).map(|(ty, _)| ty),
+            },
+        );
+        return Ok(());
+    }
+    let view_dimension = view_dimension_of(ty.resource_shape())
+        .ok_or_else(|| format!("`{}` is a {}, which has no binding", name, type_name))?;
+    if access != ResourceAccess::Read {
+        let access = match access {
+            ResourceAccess::Write => "WriteOnly",
+            ResourceAccess::ReadWrite => "ReadWrite",
+            access => {
+                return Err(format!(
+                    "`{}` is a {} with {:?} access, which wgpu can't bind",
+                    name, type_name, access
+                ));
+            }
+        };
+        let format = storage_texture_format(image_format).ok_or_else(|| {
+            format!(
+                "`{}` has format {:?}, declare it with a format wgpu supports, \
+                 e.g. [format(\"rgba16f\")]",
+                name, image_format
+            )
+        })?;
+        push_binding(
+            bindings,
+            set,
+            name.to_string(),
+            BindingKind::StorageTexture {
+                view_dimension,
+                access,
+                format,
+            },
+        );
+    } else if tl.size(ParameterCategory::DescriptorTableSlot) > 1 {
+        // WGSL has no combined samplers, so Sampler2D and friends become
+        // a texture and a sampler on adjacent bindings
+        let (sample_type, binding_type) = combined_sampler_types(ty);
+        push_binding(
+            bindings,
+            set,
+            format!("{}_texture", name),
+            BindingKind::Texture {
+                view_dimension,
+                sample_type,
+            },
+        );
+        push_binding(
+            bindings,
+            set,
+            format!("{}_sampler", name),
+            BindingKind::Sampler { binding_type },
+        );
+    } else {
+        push_binding(
+            bindings,
+            set,
+            name.to_string(),
+            BindingKind::Texture {
+                view_dimension,
+                sample_type: "Float { filterable: true }",
+            },
+        );
+    }
+    Ok(())
+}
+
+// Same rules as reflection.rs
+fn combined_sampler_types(ty: &Type) -> (&'static str, &'static str) {
+    if ty.name().is_some_and(|name| name.contains("Shadow")) {
+        return ("Depth", "Comparison");
+    }
+    let result_ty = ty.resource_result_type();
+    let scalar_type = match result_ty.kind() {
+        TypeKind::Vector => result_ty.element_type().scalar_type(),
+        _ => result_ty.scalar_type(),
+    };
+    match scalar_type {
+        ScalarType::Int8 | ScalarType::Int16 | ScalarType::Int32 | ScalarType::Int64 => {
+            ("Sint", "NonFiltering")
+        }
+        ScalarType::Uint8 | ScalarType::Uint16 | ScalarType::Uint32 | ScalarType::Uint64 => {
+            ("Uint", "NonFiltering")
+        }
+        _ => ("Float { filterable: true }", "Filtering"),
+    }
+}
+
+// Same mapping as reflection.rs, as wgpu::TextureFormat variant names
+fn storage_texture_format(format: ImageFormat) -> Option<&'static str> {
+    use ImageFormat as F;
+    Some(match format {
+        F::Rgba32f => "Rgba32Float",
+        F::Rgba16f => "Rgba16Float",
+        F::Rg32f => "Rg32Float",
+        F::Rg16f => "Rg16Float",
+        F::R11fG11fB10f => "Rg11b10Ufloat",
+        F::R32f => "R32Float",
+        F::R16f => "R16Float",
+        F::Rgba16 => "Rgba16Unorm",
+        F::Rgb10A2 => "Rgb10a2Unorm",
+        F::Rgba8 => "Rgba8Unorm",
+        F::Rg16 => "Rg16Unorm",
+        F::Rg8 => "Rg8Unorm",
+        F::R16 => "R16Unorm",
+        F::R8 => "R8Unorm",
+        F::Rgba16Snorm => "Rgba16Snorm",
+        F::Rgba8Snorm => "Rgba8Snorm",
+        F::Rg16Snorm => "Rg16Snorm",
+        F::Rg8Snorm => "Rg8Snorm",
+        F::R16Snorm => "R16Snorm",
+        F::R8Snorm => "R8Snorm",
+        F::Rgba32i => "Rgba32Sint",
+        F::Rgba16i => "Rgba16Sint",
+        F::Rgba8i => "Rgba8Sint",
+        F::Rg32i => "Rg32Sint",
+        F::Rg16i => "Rg16Sint",
+        F::Rg8i => "Rg8Sint",
+        F::R32i => "R32Sint",
+        F::R16i => "R16Sint",
+        F::R8i => "R8Sint",
+        F::Rgba32ui => "Rgba32Uint",
+        F::Rgba16ui => "Rgba16Uint",
+        F::Rgb10A2ui => "Rgb10a2Uint",
+        F::Rgba8ui => "Rgba8Uint",
+        F::Rg32ui => "Rg32Uint",
+        F::Rg16ui => "Rg16Uint",
+        F::Rg8ui => "Rg8Uint",
+        F::R32ui => "R32Uint",
+        F::R16ui => "R16Uint",
+        F::R8ui => "R8Uint",
+        F::R64ui => "R64Uint",
+        F::Bgra8 => "Bgra8Unorm",
+        _ => return None,
+    })
+}
+
+fn view_dimension_of(shape: ResourceShape) -> Option<&'static str> {
+    match shape {
+        ResourceShape::SlangTexture1d => Some("D1"),
+        ResourceShape::SlangTexture2d => Some("D2"),
+        ResourceShape::SlangTexture3d => Some("D3"),
+        ResourceShape::SlangTextureCube => Some("Cube"),
+        ResourceShape::SlangTextureCubeArray => Some("CubeArray"),
+        ResourceShape::SlangTexture2dArray => Some("D2Array"),
+        _ => None,
+    }
+}
+
+#[derive(Default)]
+struct Generator {
+    sources: Vec<String>,
+    // Slang struct name -> (layout signature, Rust name). The same Slang struct can be
+    // laid out differently in uniform and storage buffers, and then gets a second Rust struct.
+    structs: HashMap<String, Vec<(String, String)>>,
+}
+
+impl Generator {
+    // The Rust type with the same layout as `tl`, and its size in bytes.
+    // Resources have no uniform data and give None.
+    fn rust_type(&mut self, tl: &TypeLayout) -> Option<(String, usize)> {
+        let size = tl.size(ParameterCategory::Uniform);
+        match tl.kind() {
+            TypeKind::Scalar => {
+                let scalar = scalar_name(tl.ty()?.scalar_type());
+                Some((scalar.to_string(), size))
+            }
+            TypeKind::Vector => {
+                let ty = tl.ty()?;
+                let scalar = scalar_name(ty.scalar_type());
+                Some((format!("[{}; {}]", scalar, ty.element_count()), size))
+            }
+            // Rows are padded to their stride, e.g. float3x3 rows take 16 bytes
+            TypeKind::Matrix => {
+                let ty = tl.ty()?;
+                let scalar = scalar_name(ty.scalar_type());
+                let rows = ty.row_count() as usize;
+                let row_stride = size.div_ceil(rows).next_multiple_of(4);
+                let row_len = row_stride / 4;
+                Some((
+                    format!("[[{}; {}]; {}]", scalar, row_len, rows),
+                    rows * row_stride,
+                ))
+            }
+            TypeKind::Array => {
+                let count = tl.ty()?.element_count();
+                let element_tl = tl.element_type_layout();
+                let (element, element_size) = self.rust_type(element_tl)?;
+                let stride = tl.element_stride(ParameterCategory::Uniform);
+                let element = if stride > element_size {
+                    format!("Padded<{}, {}>", element, stride - element_size)
+                } else {
+                    element
+                };
+                Some((format!("[{}; {}]", element, count), count * stride))
+            }
+            TypeKind::Struct => Some(self.struct_type(tl)),
+            _ => None,
+        }
+    }
+
+    fn struct_type(&mut self, tl: &TypeLayout) -> (String, usize) {
+        let slang_name = tl.name().unwrap_or("Anonymous").to_string();
+        let size = tl.stride(ParameterCategory::Uniform);
+
+        let mut fields = Vec::new();
+        for i in 0..tl.field_count() {
+            let vl = tl.field_by_index(i).unwrap();
+            if vl.type_layout().size(ParameterCategory::Uniform) == 0 {
+                continue;
+            }
+            let Some((ty, field_size)) = self.rust_type(vl.type_layout()) else {
+                continue;
+            };
+            let name = vl.variable().map_or("unnamed", |v| v.name());
+            fields.push((
+                rust_ident(name),
+                ty,
+                vl.offset(ParameterCategory::Uniform),
+                field_size,
+            ));
+        }
+        fields.sort_by_key(|&(_, _, offset, _)| offset);
+
+        let mut signature = size.to_string();
+        for (name, ty, offset, _) in &fields {
+            write!(signature, ";{}:{}@{}", name, ty, offset).unwrap();
+        }
+        let variants = self.structs.entry(slang_name.clone()).or_default();
+        if let Some((_, rust_name)) = variants.iter().find(|(s, _)| *s == signature) {
+            return (rust_name.clone(), size);
+        }
+        let rust_name = if variants.is_empty() {
+            slang_name.clone()
+        } else {
+            format!("{}{}", slang_name, variants.len())
+        };
+        variants.push((signature, rust_name.clone()));
+        self.sources.push(struct_source(&rust_name, size, &fields));
+        (rust_name, size)
+    }
+}
+
+// A #[repr(C)] struct with padding fields filling the gaps between `fields`, given as
+// (name, type, offset, size) sorted by offset, and asserts that pin its size and offsets
+fn struct_source(
+    rust_name: &str,
+    size: usize,
+    fields: &[(String, String, usize, usize)],
+) -> String {
+    let mut source = String::new();
+    writeln!(source, "#[repr(C)]").unwrap();
+    writeln!(source, "#[derive(Clone, Copy, Debug, PartialEq)]").unwrap();
+    writeln!(source, "pub struct {} {{", rust_name).unwrap();
+    let mut cursor = 0;
+    let mut pad_count = 0;
+    for (name, ty, offset, field_size) in fields {
+        if *offset > cursor {
+            writeln!(
+                source,
+                "    pub _pad{}: [u8; {}],",
+                pad_count,
+                offset - cursor
+            )
+            .unwrap();
+            pad_count += 1;
+        }
+        writeln!(source, "    pub {}: {},", name, ty).unwrap();
+        cursor = offset + field_size;
+    }
+    if size > cursor {
+        writeln!(
+            source,
+            "    pub _pad{}: [u8; {}],",
+            pad_count,
+            size - cursor
+        )
+        .unwrap();
+    }
+    writeln!(source, "}}").unwrap();
+    writeln!(
+        source,
+        "unsafe impl bytemuck::Zeroable for {} {{}}",
+        rust_name
+    )
+    .unwrap();
+    writeln!(source, "unsafe impl bytemuck::Pod for {} {{}}", rust_name).unwrap();
+    writeln!(
+        source,
+        "const _: () = assert!(std::mem::size_of::<{}>() == {});",
+        rust_name, size
+    )
+    .unwrap();
+    for (name, _, offset, _) in fields {
+        writeln!(
+            source,
+            "const _: () = assert!(std::mem::offset_of!({}, {}) == {});",
+            rust_name, name, offset
+        )
+        .unwrap();
+    }
+    source
+}
+
+fn scalar_name(scalar: ScalarType) -> &'static str {
+    match scalar {
+        ScalarType::Float32 => "f32",
+        ScalarType::Float64 => "f64",
+        ScalarType::Int32 => "i32",
+        ScalarType::Uint32 => "u32",
+        // Half floats are kept as their bits, 32-bit bools as u32 since bool isn't Pod
+        ScalarType::Float16 => "u16",
+        ScalarType::Bool => "u32",
+        other => panic!("no Rust type for Slang scalar {:?}", other),
+    }
+}
+
+fn rust_ident(name: &str) -> String {
+    const KEYWORDS: &[&str] = &[
+        "as", "break", "const", "continue", "crate", "else", "enum", "extern", "false", "fn",
+        "for", "if", "impl", "in", "let", "loop", "match", "mod", "move", "mut", "pub", "ref",
+        "return", "static", "struct", "trait", "true", "type", "unsafe", "use", "where", "while",
+        "async", "await", "dyn", "abstract", "become", "box", "do", "final", "macro", "override",
+        "priv", "typeof", "unsized", "virtual", "yield", "try", "gen",
+    ];
+    if KEYWORDS.contains(&name) {
+        format!("r#{}", name)
+    } else {
+        name.to_string()
+    }
+}
+
+fn bind_group_source(set: usize, bindings: &[Binding]) -> String {
+    let group = format!("BindGroup{}", set);
+    let mut source = String::new();
+
+    writeln!(source, "pub struct {}Entries<'a> {{", group).unwrap();
+    for binding in bindings {
+        let (doc, ty) = match &binding.kind {
+            BindingKind::Uniform { ty, .. } => {
+                (format!("ConstantBuffer<{}>", ty), "wgpu::BufferBinding<'a>")
+            }
+            BindingKind::Storage { ty, read_only, .. } => (
+                format!(
+                    "{}StructuredBuffer<{}>",
+                    if *read_only { "" } else { "RW" },
+                    ty.as_deref().unwrap_or("?")
+                ),
+                "wgpu::BufferBinding<'a>",
+            ),
+            BindingKind::Texture { view_dimension, .. } => (
+                format!("texture, {}", view_dimension),
+                "&'a wgpu::TextureView",
+            ),
+            BindingKind::StorageTexture {
+                view_dimension,
+                access,
+                ..
+            } => (
+                format!("storage texture, {}, {}", view_dimension, access),
+                "&'a wgpu::TextureView",
+            ),
+            BindingKind::Sampler { .. } => ("sampler".to_string(), "&'a wgpu::Sampler"),
+        };
+        writeln!(source, "    // binding {}: {}", binding.binding, doc).unwrap();
+        writeln!(source, "    pub {}: {},", rust_ident(&binding.name), ty).unwrap();
+    }
+    writeln!(source, "}}").unwrap();
+    writeln!(source).unwrap();
+
+    writeln!(source, "pub struct {}(wgpu::BindGroup);", group).unwrap();
+    writeln!(source).unwrap();
+    writeln!(source, "impl {} {{", group).unwrap();
+    writeln!(source, "    pub const SET: u32 = {};", set).unwrap();
+    writeln!(
+        source,
+        "    pub const LAYOUT_ENTRIES: [wgpu::BindGroupLayoutEntry; {}] = [",
+        bindings.len()
+    )
+    .unwrap();
+    for binding in bindings {
+        let ty = match &binding.kind {
+            BindingKind::Uniform { size, .. } => format!(
+                "wgpu::BindingType::Buffer {{ ty: wgpu::BufferBindingType::Uniform, has_dynamic_offset: false, min_binding_size: std::num::NonZeroU64::new({}) }}",
+                size
+            ),
+            BindingKind::Storage {
+                read_only, size, ..
+            } => format!(
+                "wgpu::BindingType::Buffer {{ ty: wgpu::BufferBindingType::Storage {{ read_only: {} }}, has_dynamic_offset: false, min_binding_size: std::num::NonZeroU64::new({}) }}",
+                read_only, size
+            ),
+            BindingKind::Texture {
+                view_dimension,
+                sample_type,
+            } => format!(
+                "wgpu::BindingType::Texture {{ sample_type: wgpu::TextureSampleType::{}, view_dimension: wgpu::TextureViewDimension::{}, multisampled: false }}",
+                sample_type, view_dimension
+            ),
+            BindingKind::StorageTexture {
+                view_dimension,
+                access,
+                format,
+            } => format!(
+                "wgpu::BindingType::StorageTexture {{ access: wgpu::StorageTextureAccess::{}, format: wgpu::TextureFormat::{}, view_dimension: wgpu::TextureViewDimension::{} }}",
+                access, format, view_dimension
+            ),
+            BindingKind::Sampler { binding_type } => format!(
+                "wgpu::BindingType::Sampler(wgpu::SamplerBindingType::{})",
+                binding_type
+            ),
+        };
+        writeln!(source, "        wgpu::BindGroupLayoutEntry {{").unwrap();
+        writeln!(source, "            binding: {},", binding.binding).unwrap();
+        writeln!(source, "            visibility: wgpu::ShaderStages::all(),").unwrap();
+        writeln!(source, "            ty: {},", ty).unwrap();
+        writeln!(source, "            count: None,").unwrap();
+        writeln!(source, "        }},").unwrap();
+    }
+    writeln!(source, "    ];").unwrap();
+    writeln!(source).unwrap();
+    writeln!(
+        source,
+        "    pub fn create_bind_group_layout(device: &wgpu::Device) -> wgpu::BindGroupLayout {{"
+    )
+    .unwrap();
+    writeln!(
+        source,
+        "        device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {{"
+    )
+    .unwrap();
+    writeln!(source, "            label: Some(\"{}\"),", group).unwrap();
+    writeln!(source, "            entries: &Self::LAYOUT_ENTRIES,").unwrap();
+    writeln!(source, "        }})").unwrap();
+    writeln!(source, "    }}").unwrap();
+    writeln!(source).unwrap();
+    writeln!(
+        source,
+        "    pub fn new(device: &wgpu::Device, layout: &wgpu::BindGroupLayout, entries: {}Entries) -> Self {{",
+        group
+    )
+    .unwrap();
+    writeln!(
+        source,
+        "        {}(device.create_bind_group(&wgpu::BindGroupDescriptor {{",
+        group
+    )
+    .unwrap();
+    writeln!(source, "            label: Some(\"{}\"),", group).unwrap();
+    writeln!(source, "            layout,").unwrap();
+    writeln!(source, "            entries: &[").unwrap();
+    for binding in bindings {
+        let resource = match binding.kind {
+            BindingKind::Uniform { .. } | BindingKind::Storage { .. } => "Buffer",
+            BindingKind::Texture { .. } | BindingKind::StorageTexture { .. } => "TextureView",
+            BindingKind::Sampler { .. } => "Sampler",
+        };
+        writeln!(
+            source,
+            "                wgpu::BindGroupEntry {{ binding: {}, resource: wgpu::BindingResource::{}(entries.{}) }},",
+            binding.binding,
+            resource,
+            rust_ident(&binding.name)
+        )
+        .unwrap();
+    }
+    writeln!(source, "            ],").unwrap();
+    writeln!(source, "        }}))").unwrap();
+    writeln!(source, "    }}").unwrap();
+    writeln!(source).unwrap();
+    writeln!(
+        source,
+        "    pub fn bind_group(&self) -> &wgpu::BindGroup {{"
+    )
+    .unwrap();
+    writeln!(source, "        &self.0").unwrap();
+    writeln!(source, "    }}").unwrap();
+    writeln!(source, "}}").unwrap();
+    source
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn storage_formats_match_reflection() {
+        use ImageFormat as F;
+        let formats = [
+            F::Unknown,
+            F::Rgba32f,
+            F::Rgba16f,
+            F::Rg32f,
+            F::Rg16f,
+            F::R11fG11fB10f,
+            F::R32f,
+            F::R16f,
+            F::Rgba16,
+            F::Rgb10A2,
+            F::Rgba8,
+            F::Rg16,
+            F::Rg8,
+            F::R16,
+            F::R8,
+            F::Rgba16Snorm,
+            F::Rgba8Snorm,
+            F::Rg16Snorm,
+            F::Rg8Snorm,
+            F::R16Snorm,
+            F::R8Snorm,
+            F::Rgba32i,
+            F::Rgba16i,
+            F::Rgba8i,
+            F::Rg32i,
+            F::Rg16i,
+            F::Rg8i,
+            F::R32i,
+            F::R16i,
+            F::R8i,
+            F::Rgba32ui,
+            F::Rgba16ui,
+            F::Rgb10A2ui,
+            F::Rgba8ui,
+            F::Rg32ui,
+            F::Rg16ui,
+            F::Rg8ui,
+            F::R32ui,
+            F::R16ui,
+            F::R8ui,
+            F::R64ui,
+            F::R64i,
+            F::Bgra8,
+        ];
+        for format in formats {
+            assert_eq!(
+                storage_texture_format(format).map(str::to_string),
+                crate::reflection::storage_texture_format(format).map(|f| format!("{:?}", f)),
+                "{:?}",
+                format
+            );
+        }
+    }
+
+    #[test]
+    fn struct_source_pads_gaps_and_asserts_offsets() {
+        // float3 a; float b; float2 c; float3 d; in a uniform buffer
+        let field =
+            |name: &str, ty: &str, offset, size| (name.to_string(), ty.to_string(), offset, size);
+        let fields = [
+            field("a", "[f32; 3]", 0, 12),
+            field("b", "f32", 12, 4),
+            field("c", "[f32; 2]", 16, 8),
+            field("d", "[f32; 3]", 32, 12),
+        ];
+        let source = struct_source("Light", 48, &fields);
+        let body = concat!(
+            "pub struct Light {\n",
+            "    pub a: [f32; 3],\n",
+            "    pub b: f32,\n",
+            "    pub c: [f32; 2],\n",
+            "    pub _pad0: [u8; 8],\n",
+            "    pub d: [f32; 3],\n",
+            "    pub _pad1: [u8; 4],\n",
+            "}\n",
+        );
+        assert!(source.contains(body), "{}", source);
+        assert!(source.contains("assert!(std::mem::size_of::<Light>() == 48);"));
+        for (name, offset) in [("a", 0), ("b", 12), ("c", 16), ("d", 32)] {
+            let assert = format!(
+                "assert!(std::mem::offset_of!(Light, {}) == {});",
+                name, offset
+            );
+            assert!(source.contains(&assert), "{}", assert);
+        }
+    }
+}
@@ -0,0 +1,152 @@
+// Slang source for types that are shared between Rust and shaders.
+// Types derive SlangType, get added to a SlangModule, and the module is written
+// out as a .slang file that the shaders `import`.
+
+use std::{fs, io, path::Path};
+
+use cgmath::{Matrix2, Matrix3, Matrix4, Vector2, Vector3, Vector4};
+
+pub use proc_macros::SlangType;
+
+pub trait SlangType {
+    // How the type is spelled in Slang source, e.g. `float3` or `StructuredBuffer<Hermite>`
+    fn slang_name() -> String;
+
+    // Adds the declarations this type needs to the module. Built-in types need none.
+    fn declare(_module: &mut SlangModule) {}
+}
+
+pub struct SlangModule {
+    name: String,
+    // (type name, declaration) in dependency order
+    declarations: Vec<(String, String)>,
+}
+
+impl SlangModule {
+    pub fn new(name: &str) -> Self {
+        SlangModule {
+            name: name.to_string(),
+            declarations: Vec::new(),
+        }
+    }
+
+    pub fn with<T: SlangType>(mut self) -> Self {
+        self.add::<T>();
+        self
+    }
+
+    // Declares T along with every type its fields depend on
+    pub fn add<T: SlangType>(&mut self) {
+        T::declare(self);
+    }
+
+    pub fn contains(&self, type_name: &str) -> bool {
+        self.declarations.iter().any(|(name, _)| name == type_name)
+    }
+
+    // Called from derived `declare` after the field types have been declared
+    pub fn push_declaration(&mut self, type_name: String, source: String) {
+        if !self.contains(&type_name) {
+            self.declarations.push((type_name, source));
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn source(&self) -> String {
+        let mut source = String::from("// Generated from Rust types by wgpu_tidy. Do not edit.\n");
+        for (_, declaration) in &self.declarations {
+            source.push('\n');
+            source.push_str(declaration);
+        }
+        source
+    }
+
+    // Writes `<dir>/<name>.slang`. The file is left alone when nothing changed,
+    // so its modification time only moves when the types do.
+    pub fn write_to(&self, dir: impl AsRef<Path>) -> io::Result<()> {
+        let path = dir.as_ref().join(format!("{}.slang", self.name));
+        let source = self.source();
+        if fs::read_to_string(&path).is_ok_and(|old| old == source) {
+            return Ok(());
+        }
+        fs::write(path, source)
+    }
+}
+
+macro_rules! builtin_slang_type {
+    ($($ty:ty => $name:literal),* $(,)?) => {
+        $(
+            impl SlangType for $ty {
+                fn slang_name() -> String {
+                    $name.to_string()
+                }
+            }
+        )*
+    };
+}
+
+builtin_slang_type! {
+    f32 => "float",
+    i32 => "int",
+    u32 => "uint",
+    bool => "bool",
+    Vector2<f32> => "float2",
+    Vector3<f32> => "float3",
+    Vector4<f32> => "float4",
+    Vector2<i32> => "int2",
+    Vector3<i32> => "int3",
+    Vector4<i32> => "int4",
+    Matrix2<f32> => "float2x2",
+    Matrix3<f32> => "float3x3",
+    Matrix4<f32> => "float4x4",
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(SlangType)]
+    struct Inner {
+        weight: f32,
+    }
+
+    #[derive(SlangType)]
+    #[slang(name = "Outer", implements = "IDifferentiable, IFoo")]
+    struct RustOuter {
+        inner: Inner,
+        #[slang(name = "dir")]
+        direction: Vector3<f32>,
+        #[slang(skip)]
+        _cache: Vec<u8>,
+    }
+
+    #[test]
+    fn source_declares_dependencies_first() {
+        let module = SlangModule::new("test").with::<RustOuter>().with::<Inner>();
+        assert_eq!(
+            module.source(),
+            "// Generated from Rust types by wgpu_tidy. Do not edit.\n\
+             \n\
+             struct Inner\n\
+             {\n    float weight;\n}\n\
+             \n\
+             struct Outer : IDifferentiable, IFoo\n\
+             {\n    Inner inner;\n    float3 dir;\n}\n"
+        );
+    }
+
+    #[test]
+    fn builtins_need_no_declaration() {
+        let module = SlangModule::new("test")
+            .with::<f32>()
+            .with::<Matrix3<f32>>();
+        assert!(!module.contains("float"));
+        assert_eq!(
+            module.source(),
+            "// Generated from Rust types by wgpu_tidy. Do not edit.\n"
+        );
+    }
+}
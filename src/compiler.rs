@@ -0,0 +1,447 @@
+use std::{
+    collections::HashMap,
+    error::Error,
+    ffi::CString,
+    fmt,
+    path::{Path, PathBuf},
+};
+
+use slang::{Downcast, reflection::TypeLayout};
+use wgpu::{BindGroupLayout, BindGroupLayoutDescriptor, BindGroupLayoutEntry};
+
+use crate::{
+    diagnostics::{Diagnostic, error_log, parse_diagnostics},
+    reflection::{Cursor, base_layout_entries, parameter_tree},
+    shader_cache::{CacheInputs, ShaderCache},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CompileStage {
+    LoadModule,
+    Compose,
+    Link,
+    TargetCode,
+    Reflection,
+}
+
+impl fmt::Display for CompileStage {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CompileStage::LoadModule => write!(f, "load"),
+            CompileStage::Compose => write!(f, "compose"),
+            CompileStage::Link => write!(f, "link"),
+            CompileStage::TargetCode => write!(f, "generate code for"),
+            CompileStage::Reflection => write!(f, "reflect"),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum ShaderError {
+    GlobalSession,
+    Session,
+    InvalidSearchPath(PathBuf),
+    MissingEntryPoint {
+        module: String,
+        name: String,
+    },
+    NoComputeEntryPoints {
+        module: String,
+    },
+    // `log` is Slang's raw output, for when it doesn't parse into diagnostics
+    Compile {
+        module: String,
+        stage: CompileStage,
+        diagnostics: Vec<Diagnostic>,
+        log: Option<String>,
+    },
+}
+
+impl ShaderError {
+    fn compile(module: &str, stage: CompileStage, e: slang::Error) -> Self {
+        let log = error_log(&e);
+        ShaderError::Compile {
+            module: module.to_string(),
+            stage,
+            diagnostics: log.as_deref().map(parse_diagnostics).unwrap_or_default(),
+            log,
+        }
+    }
+
+    pub fn diagnostics(&self) -> &[Diagnostic] {
+        match self {
+            ShaderError::Compile { diagnostics, .. } => diagnostics,
+            _ => &[],
+        }
+    }
+}
+
+impl fmt::Display for ShaderError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ShaderError::GlobalSession => write!(f, "could not create the Slang global session"),
+            ShaderError::Session => write!(f, "could not create a Slang session"),
+            ShaderError::InvalidSearchPath(path) => {
+                write!(
+                    f,
+                    "search path {} is not valid UTF-8 without nul bytes",
+                    path.display()
+                )
+            }
+            ShaderError::MissingEntryPoint { module, name } => {
+                write!(f, "{} has no entry point `{}`", module, name)
+            }
+            ShaderError::NoComputeEntryPoints { module } => {
+                write!(f, "{} has no [shader(\"compute\")] entry points", module)
+            }
+            ShaderError::Compile {
+                module,
+                stage,
+                diagnostics,
+                log,
+            } => {
+                write!(f, "failed to {} {}", stage, module)?;
+                if !diagnostics.is_empty() {
+                    for diagnostic in diagnostics {
+                        write!(f, "\n{}", diagnostic)?;
+                    }
+                } else if let Some(log) = log {
+                    write!(f, "\n{}", log.trim_end())?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl Error for ShaderError {}
+
+// What Slang compiles to and how wgpu receives it. SPIR-V avoids limits of Slang's WGSL
+// emitter, wgpu translates it with naga either way.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ShaderBackend {
+    Wgsl,
+    SpirV,
+}
+
+impl ShaderBackend {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "wgsl" => Some(ShaderBackend::Wgsl),
+            "spirv" | "spir-v" => Some(ShaderBackend::SpirV),
+            _ => None,
+        }
+    }
+
+    fn compile_target(self) -> slang::CompileTarget {
+        match self {
+            ShaderBackend::Wgsl => slang::CompileTarget::Wgsl,
+            ShaderBackend::SpirV => slang::CompileTarget::Spirv,
+        }
+    }
+
+    fn default_profile(self) -> &'static str {
+        match self {
+            ShaderBackend::Wgsl => "glsl_450",
+            ShaderBackend::SpirV => "spirv_1_5",
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            ShaderBackend::Wgsl => "wgsl",
+            ShaderBackend::SpirV => "spirv",
+        }
+    }
+}
+
+// Builds a fresh slang session for every compile, so edited modules are always reloaded.
+pub struct ShaderCompiler {
+    global_session: slang::GlobalSession,
+    search_paths: Vec<PathBuf>,
+    macros: Vec<(String, String)>,
+    backend: ShaderBackend,
+    profile: String,
+    entry_points: Vec<String>,
+    all_entry_points: bool,
+    cache: Option<ShaderCache>,
+}
+
+impl ShaderCompiler {
+    // Targets WGSL with the glsl_450 profile until told otherwise
+    pub fn new() -> Result<Self, ShaderError> {
+        Ok(ShaderCompiler {
+            global_session: slang::GlobalSession::new().ok_or(ShaderError::GlobalSession)?,
+            search_paths: Vec::new(),
+            macros: Vec::new(),
+            backend: ShaderBackend::Wgsl,
+            profile: ShaderBackend::Wgsl.default_profile().to_string(),
+            entry_points: Vec::new(),
+            all_entry_points: false,
+            cache: None,
+        })
+    }
+
+    pub fn search_path(mut self, path: impl AsRef<Path>) -> Self {
+        self.search_paths.push(path.as_ref().to_path_buf());
+        self
+    }
+
+    pub fn search_paths(&self) -> &[PathBuf] {
+        &self.search_paths
+    }
+
+    pub fn define(mut self, name: &str, value: &str) -> Self {
+        self.macros.push((name.to_string(), value.to_string()));
+        self
+    }
+
+    // Also resets the profile to the backend's default
+    pub fn backend(mut self, backend: ShaderBackend) -> Self {
+        self.backend = backend;
+        self.profile = backend.default_profile().to_string();
+        self
+    }
+
+    pub fn profile(mut self, profile: &str) -> Self {
+        self.profile = profile.to_string();
+        self
+    }
+
+    pub fn entry_point(mut self, name: &str) -> Self {
+        self.entry_points.push(name.to_string());
+        self
+    }
+
+    // Links every `[shader(...)]` entry point the module defines, besides the named ones
+    pub fn all_entry_points(mut self) -> Self {
+        self.all_entry_points = true;
+        self
+    }
+
+    // Reuses code emitted by an earlier compile with the same sources and options. The module is
+    // still loaded and linked for its reflection, only code generation is skipped. The key covers
+    // every .slang file in the search paths, so editing any of them misses.
+    pub fn cache_dir(mut self, dir: impl AsRef<Path>) -> Self {
+        self.cache = Some(ShaderCache::new(dir));
+        self
+    }
+
+    // None when there's no cache or the sources can't be read, which just means compiling
+    fn cache_key(&self, module_name: &str) -> Option<(&ShaderCache, String)> {
+        let cache = self.cache.as_ref()?;
+        let key = cache
+            .key(&CacheInputs {
+                module: module_name,
+                search_paths: &self.search_paths,
+                macros: &self.macros,
+                target: self.backend.name(),
+                profile: &self.profile,
+                entry_points: &self.entry_points,
+                all_entry_points: self.all_entry_points,
+            })
+            .ok()?;
+        Some((cache, key))
+    }
+
+    pub fn compile(&self, module_name: &str) -> Result<ShaderProgram, ShaderError> {
+        // SPIR-V would otherwise name every entry point `main`
+        let mut session_options = slang::CompilerOptions::default()
+            .matrix_layout_row(true)
+            .vulkan_use_entry_point_name(true);
+        for (name, value) in &self.macros {
+            session_options = session_options.macro_define(name, value);
+        }
+
+        let target_desc = slang::TargetDesc::default()
+            .format(self.backend.compile_target())
+            .profile(self.global_session.find_profile(&self.profile));
+
+        let targets = [target_desc];
+        let search_paths = self
+            .search_paths
+            .iter()
+            .map(|p| {
+                p.to_str()
+                    .and_then(|s| CString::new(s).ok())
+                    .ok_or_else(|| ShaderError::InvalidSearchPath(p.clone()))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        let search_path_ptrs: Vec<_> = search_paths.iter().map(|p| p.as_ptr()).collect();
+
+        let session_desc = slang::SessionDesc::default()
+            .targets(&targets)
+            .search_paths(&search_path_ptrs)
+            .options(&session_options);
+
+        let session = self
+            .global_session
+            .create_session(&session_desc)
+            .ok_or(ShaderError::Session)?;
+
+        let module = session
+            .load_module(module_name)
+            .map_err(|e| ShaderError::compile(module_name, CompileStage::LoadModule, e))?;
+
+        let mut components = vec![module.downcast().clone()];
+        for name in &self.entry_points {
+            let entry_point = module.find_entry_point_by_name(name).ok_or_else(|| {
+                ShaderError::MissingEntryPoint {
+                    module: module_name.to_string(),
+                    name: name.clone(),
+                }
+            })?;
+            // Otherwise it's linked below with the rest
+            if !self.all_entry_points {
+                components.push(entry_point.downcast().clone());
+            }
+        }
+        if self.all_entry_points {
+            for i in 0..module.entry_point_count() {
+                if let Some(entry_point) = module.entry_point_by_index(i) {
+                    components.push(entry_point.downcast().clone());
+                }
+            }
+        }
+        let program = session
+            .create_composite_component_type(&components)
+            .map_err(|e| ShaderError::compile(module_name, CompileStage::Compose, e))?;
+
+        let linked = program
+            .link()
+            .map_err(|e| ShaderError::compile(module_name, CompileStage::Link, e))?;
+        let reflection = linked
+            .layout(0)
+            .map_err(|e| ShaderError::compile(module_name, CompileStage::Reflection, e))?;
+        let layout_entries = base_layout_entries(reflection.global_params_type_layout());
+        let entry_points = (0..reflection.entry_point_count())
+            .filter_map(|i| reflection.entry_point_by_index(i))
+            .map(|e| e.name().to_string())
+            .collect();
+
+        // The program above is linked either way, since `ShaderProgram` reflects through it.
+        // Only code generation is skipped on a hit.
+        let cache_key = self.cache_key(module_name);
+        let summary = cache_key
+            .as_ref()
+            .map(|_| parameter_tree("", Cursor::fresh(reflection.global_params_type_layout())));
+        let cached_code = cache_key
+            .as_ref()
+            .zip(summary.as_ref())
+            .and_then(|((cache, key), summary)| cache.load(key, summary));
+        let from_cache = cached_code.is_some();
+        let code = match cached_code {
+            Some(code) => code,
+            None => {
+                let code = linked
+                    .target_code(0)
+                    .map_err(|e| ShaderError::compile(module_name, CompileStage::TargetCode, e))?
+                    .as_slice()
+                    .to_vec();
+                if let Some(((cache, key), summary)) = cache_key.as_ref().zip(summary.as_ref())
+                    && let Err(e) = cache.store(key, &code, summary)
+                {
+                    eprintln!("Failed to cache {}: {}", module_name, e);
+                }
+                code
+            }
+        };
+
+        Ok(ShaderProgram {
+            _session: session,
+            linked,
+            code,
+            module: module_name.to_string(),
+            from_cache,
+            backend: self.backend,
+            entry_points,
+            layout_entries,
+        })
+    }
+}
+
+// A linked program with its target code and the bind group layouts derived from its reflection
+pub struct ShaderProgram {
+    // Owns the modules the linked program was built from
+    _session: slang::Session,
+    linked: slang::ComponentType,
+    module: String,
+    code: Vec<u8>,
+    from_cache: bool,
+    backend: ShaderBackend,
+    entry_points: Vec<String>,
+    layout_entries: HashMap<usize, Vec<BindGroupLayoutEntry>>,
+}
+
+impl ShaderProgram {
+    pub fn module(&self) -> &str {
+        &self.module
+    }
+
+    pub fn code(&self) -> &[u8] {
+        &self.code
+    }
+
+    pub fn backend(&self) -> ShaderBackend {
+        self.backend
+    }
+
+    // Whether the code came from the shader cache rather than Slang
+    pub fn from_cache(&self) -> bool {
+        self.from_cache
+    }
+
+    // Text targets such as WGSL
+    pub fn code_str(&self) -> Option<&str> {
+        std::str::from_utf8(&self.code).ok()
+    }
+
+    // Every linked entry point, in the order Slang reflects them
+    pub fn entry_points(&self) -> &[String] {
+        &self.entry_points
+    }
+
+    // Already checked to succeed in `ShaderCompiler::compile`
+    pub fn reflection(&self) -> &slang::reflection::Shader {
+        self.linked.layout(0).unwrap()
+    }
+
+    pub fn global_type_layout(&self) -> &TypeLayout {
+        self.reflection().global_params_type_layout()
+    }
+
+    pub fn layout_entries(&self) -> &HashMap<usize, Vec<BindGroupLayoutEntry>> {
+        &self.layout_entries
+    }
+
+    pub fn create_bind_group_layouts(
+        &self,
+        device: &wgpu::Device,
+    ) -> HashMap<usize, BindGroupLayout> {
+        self.layout_entries
+            .iter()
+            .map(|(&k, v)| {
+                (
+                    k,
+                    device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+                        label: None,
+                        entries: v,
+                    }),
+                )
+            })
+            .collect()
+    }
+
+    pub fn create_shader_module(&self, device: &wgpu::Device) -> wgpu::ShaderModule {
+        let source = match self.backend {
+            ShaderBackend::Wgsl => {
+                wgpu::ShaderSource::Wgsl(std::borrow::Cow::Borrowed(self.code_str().unwrap()))
+            }
+            ShaderBackend::SpirV => wgpu::util::make_spirv(&self.code),
+        };
+        device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: None,
+            source,
+        })
+    }
+}
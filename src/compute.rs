@@ -0,0 +1,249 @@
+// Compute kernels of a linked Slang program, one pipeline per `[shader("compute")]` entry point.
+// Bind group layouts come from the same reflection as the render pipeline's, and the workgroup
+// size of each kernel is read from its `[numthreads]`, so dispatches can be sized in elements.
+
+use std::collections::HashMap;
+
+use wgpu::{
+    BindGroup, BindGroupDescriptor, BindGroupLayout, BindGroupLayoutDescriptor,
+    BindGroupLayoutEntry, ComputePass, ComputePipeline, ComputePipelineDescriptor,
+    PipelineCompilationOptions, PipelineLayoutDescriptor, ShaderStages,
+};
+
+use crate::{
+    compiler::{ShaderError, ShaderProgram},
+    reflection::{BindingResources, bind_group_entries_from_layout},
+};
+
+pub struct ComputeKernel {
+    pipeline: ComputePipeline,
+    workgroup_size: [u32; 3],
+}
+
+impl ComputeKernel {
+    pub fn pipeline(&self) -> &ComputePipeline {
+        &self.pipeline
+    }
+
+    pub fn workgroup_size(&self) -> [u32; 3] {
+        self.workgroup_size
+    }
+
+    pub fn workgroup_counts(&self, extent: [u32; 3]) -> [u32; 3] {
+        workgroup_counts(self.workgroup_size, extent)
+    }
+}
+
+// Enough workgroups to cover `extent` elements along each axis
+fn workgroup_counts(workgroup_size: [u32; 3], extent: [u32; 3]) -> [u32; 3] {
+    [0, 1, 2].map(|i| extent[i].div_ceil(workgroup_size[i]))
+}
+
+pub struct ComputeProgram {
+    program: ShaderProgram,
+    layout_entries: HashMap<usize, Vec<BindGroupLayoutEntry>>,
+    bind_group_layouts: HashMap<usize, BindGroupLayout>,
+    kernels: HashMap<String, ComputeKernel>,
+}
+
+impl ComputeProgram {
+    // Every compute entry point linked into `program` becomes a kernel
+    pub fn new(device: &wgpu::Device, program: ShaderProgram) -> Result<Self, ShaderError> {
+        Self::with_constants(device, program, &HashMap::new())
+    }
+
+    // `constants` set the program's overridable constants, see `PipelineCompilationOptions`
+    pub fn with_constants(
+        device: &wgpu::Device,
+        program: ShaderProgram,
+        constants: &HashMap<String, f64>,
+    ) -> Result<Self, ShaderError> {
+        // Reflection makes every binding visible to all stages, which wgpu refuses for writable
+        // storage, since the vertex stage can't have it
+        let layout_entries: HashMap<usize, Vec<BindGroupLayoutEntry>> = program
+            .layout_entries()
+            .iter()
+            .map(|(&set, entries)| {
+                let entries = entries
+                    .iter()
+                    .map(|entry| BindGroupLayoutEntry {
+                        visibility: ShaderStages::COMPUTE,
+                        ..*entry
+                    })
+                    .collect();
+                (set, entries)
+            })
+            .collect();
+        let bind_group_layouts: HashMap<usize, BindGroupLayout> = layout_entries
+            .iter()
+            .map(|(&set, entries)| {
+                (
+                    set,
+                    device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+                        label: Some(&format!("compute_bgl{}", set)),
+                        entries,
+                    }),
+                )
+            })
+            .collect();
+
+        // Pipeline layout slots have to follow the set indices
+        let mut sets: Vec<_> = bind_group_layouts.iter().collect();
+        sets.sort_by_key(|(set, _)| **set);
+        let sorted_layouts: Vec<&BindGroupLayout> = sets.into_iter().map(|(_, l)| l).collect();
+        let pipeline_layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
+            label: Some("compute_pipeline_layout"),
+            bind_group_layouts: &sorted_layouts,
+            push_constant_ranges: &[],
+        });
+        let shader_module = program.create_shader_module(device);
+
+        let reflection = program.reflection();
+        let mut kernels = HashMap::new();
+        for i in 0..reflection.entry_point_count() {
+            let Some(entry_point) = reflection.entry_point_by_index(i) else {
+                continue;
+            };
+            if entry_point.stage() != slang::Stage::Compute {
+                continue;
+            }
+            let name = entry_point.name();
+            let workgroup_size = entry_point
+                .compute_thread_group_size()
+                .map(|n| u32::try_from(n).unwrap().max(1));
+            let pipeline = device.create_compute_pipeline(&ComputePipelineDescriptor {
+                label: Some(name),
+                layout: Some(&pipeline_layout),
+                module: &shader_module,
+                entry_point: Some(name),
+                compilation_options: PipelineCompilationOptions {
+                    constants,
+                    ..Default::default()
+                },
+                cache: None,
+            });
+            kernels.insert(
+                name.to_string(),
+                ComputeKernel {
+                    pipeline,
+                    workgroup_size,
+                },
+            );
+        }
+        if kernels.is_empty() {
+            return Err(ShaderError::NoComputeEntryPoints {
+                module: program.module().to_string(),
+            });
+        }
+
+        Ok(ComputeProgram {
+            program,
+            layout_entries,
+            bind_group_layouts,
+            kernels,
+        })
+    }
+
+    pub fn program(&self) -> &ShaderProgram {
+        &self.program
+    }
+
+    pub fn layout_entries(&self) -> &HashMap<usize, Vec<BindGroupLayoutEntry>> {
+        &self.layout_entries
+    }
+
+    pub fn bind_group_layouts(&self) -> &HashMap<usize, BindGroupLayout> {
+        &self.bind_group_layouts
+    }
+
+    pub fn kernel_names(&self) -> impl Iterator<Item = &str> {
+        self.kernels.keys().map(String::as_str)
+    }
+
+    pub fn kernel(&self, name: &str) -> Option<&ComputeKernel> {
+        self.kernels.get(name)
+    }
+
+    // Resources are expected to be written through the program's reflection beforehand
+    pub fn create_bind_groups(
+        &self,
+        device: &wgpu::Device,
+        binding_resources: &BindingResources,
+    ) -> HashMap<usize, BindGroup> {
+        bind_group_entries_from_layout(&self.layout_entries, binding_resources)
+            .iter()
+            .map(|(&set, entries)| {
+                (
+                    set,
+                    device.create_bind_group(&BindGroupDescriptor {
+                        label: Some(&format!("compute_bg{}", set)),
+                        layout: &self.bind_group_layouts[&set],
+                        entries,
+                    }),
+                )
+            })
+            .collect()
+    }
+
+    // Runs `kernel` over `n_elements` along x
+    pub fn dispatch_for(
+        &self,
+        pass: &mut ComputePass,
+        kernel: &str,
+        bind_groups: &HashMap<usize, BindGroup>,
+        n_elements: u32,
+    ) {
+        self.dispatch_for_extent(pass, kernel, bind_groups, [n_elements, 1, 1]);
+    }
+
+    // Panics if there's no kernel by that name
+    pub fn dispatch_for_extent(
+        &self,
+        pass: &mut ComputePass,
+        kernel: &str,
+        bind_groups: &HashMap<usize, BindGroup>,
+        extent: [u32; 3],
+    ) {
+        let Some(compute_kernel) = self.kernels.get(kernel) else {
+            panic!(
+                "{} has no compute kernel `{}`",
+                self.program.module(),
+                kernel
+            );
+        };
+        pass.set_pipeline(&compute_kernel.pipeline);
+        for (&set, bind_group) in bind_groups {
+            pass.set_bind_group(set as u32, bind_group, &[]);
+        }
+        let [x, y, z] = compute_kernel.workgroup_counts(extent);
+        pass.dispatch_workgroups(x, y, z);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn partial_workgroups_round_up() {
+        assert_eq!(workgroup_counts([64, 1, 1], [100, 1, 1]), [2, 1, 1]);
+        assert_eq!(workgroup_counts([64, 1, 1], [1, 1, 1]), [1, 1, 1]);
+    }
+
+    #[test]
+    fn exact_multiples_add_no_workgroup() {
+        assert_eq!(workgroup_counts([64, 1, 1], [128, 1, 1]), [2, 1, 1]);
+    }
+
+    #[test]
+    fn empty_extents_dispatch_nothing() {
+        assert_eq!(workgroup_counts([8, 8, 1], [0, 16, 1]), [0, 2, 1]);
+        assert_eq!(workgroup_counts([8, 8, 1], [0, 0, 0]), [0, 0, 0]);
+    }
+
+    // Each axis against its own side of the workgroup, as `[numthreads(8, 4, 2)]` reflects
+    #[test]
+    fn extents_are_divided_per_axis() {
+        assert_eq!(workgroup_counts([8, 4, 2], [17, 8, 3]), [3, 2, 2]);
+    }
+}
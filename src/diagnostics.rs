@@ -0,0 +1,218 @@
+// Slang reports problems as text, one header line per diagnostic in the form
+//     path(line[,column]): severity [code]: message
+// optionally followed by the offending source line and a `^` marker under the column.
+// This file is also compiled into build.rs, so it may only depend on slang and std.
+
+use std::fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    Note,
+    Warning,
+    Error,
+    Internal,
+}
+
+impl Severity {
+    fn parse(s: &str) -> Option<Self> {
+        match s {
+            "note" => Some(Severity::Note),
+            "warning" => Some(Severity::Warning),
+            "error" | "fatal error" => Some(Severity::Error),
+            "internal error" => Some(Severity::Internal),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Severity::Note => write!(f, "note"),
+            Severity::Warning => write!(f, "warning"),
+            Severity::Error => write!(f, "error"),
+            Severity::Internal => write!(f, "internal error"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Diagnostic {
+    pub file: Option<String>,
+    pub line: Option<u32>,
+    pub column: Option<u32>,
+    pub severity: Severity,
+    pub code: Option<u32>,
+    pub message: String,
+    // Source excerpt Slang printed under the header, if any
+    pub excerpt: Vec<String>,
+}
+
+impl Diagnostic {
+    fn parse_header(line: &str) -> Option<Self> {
+        let (file, position, rest) = match line.split_once("): ") {
+            Some((location, rest)) => {
+                let (file, position) = location.rsplit_once('(')?;
+                (Some(file), Some(position), rest)
+            }
+            None => (None, None, line),
+        };
+        let (line_number, column) = match position {
+            Some(position) => {
+                let mut numbers = position.split(',').map(|n| n.trim().parse::<u32>().ok());
+                let line_number = numbers.next()??;
+                (Some(line_number), numbers.next().flatten())
+            }
+            None => (None, None),
+        };
+
+        let (kind, message) = rest.split_once(": ")?;
+        let (severity, code) = match kind.rsplit_once(' ') {
+            Some((severity, code)) if code.chars().all(|c| c.is_ascii_digit()) => {
+                (severity, code.parse().ok())
+            }
+            _ => (kind, None),
+        };
+        Some(Diagnostic {
+            file: file.filter(|f| !f.is_empty()).map(str::to_string),
+            line: line_number,
+            column,
+            severity: Severity::parse(severity.trim())?,
+            code,
+            message: message.trim().to_string(),
+            excerpt: Vec::new(),
+        })
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if let Some(file) = &self.file {
+            write!(f, "{}:", file)?;
+            if let Some(line) = self.line {
+                write!(f, "{}:", line)?;
+            }
+            if let Some(column) = self.column {
+                write!(f, "{}:", column)?;
+            }
+            write!(f, " ")?;
+        }
+        write!(f, "{}", self.severity)?;
+        if let Some(code) = self.code {
+            write!(f, " {}", code)?;
+        }
+        write!(f, ": {}", self.message)?;
+        for line in &self.excerpt {
+            write!(f, "\n    {}", line)?;
+        }
+        Ok(())
+    }
+}
+
+// Lines that aren't a header belong to the diagnostic above them
+pub fn parse_diagnostics(log: &str) -> Vec<Diagnostic> {
+    let mut diagnostics: Vec<Diagnostic> = Vec::new();
+    for line in log.lines() {
+        if let Some(diagnostic) = Diagnostic::parse_header(line) {
+            diagnostics.push(diagnostic);
+            continue;
+        }
+        let Some(last) = diagnostics.last_mut() else {
+            continue;
+        };
+        if line.trim().is_empty() {
+            continue;
+        }
+        // The caret sits under the column in the excerpt line before it
+        if line.trim_start().starts_with('^') && last.column.is_none() && !last.excerpt.is_empty() {
+            let caret = line.find('^').unwrap();
+            last.column = Some(caret as u32 + 1);
+        }
+        last.excerpt.push(line.trim_end().to_string());
+    }
+    diagnostics
+}
+
+// The diagnostic log attached to a slang error, if Slang produced one
+pub fn error_log(e: &slang::Error) -> Option<String> {
+    match e {
+        slang::Error::Blob(blob) => Some(String::from_utf8_lossy(blob.as_slice()).into_owned()),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn header_with_file_position_and_code() {
+        let diagnostics = parse_diagnostics(
+            "src/shader/gorilla.slang(12, 5): error 30015: undefined identifier 'foo'.\n",
+        );
+        assert_eq!(
+            diagnostics,
+            [Diagnostic {
+                file: Some("src/shader/gorilla.slang".into()),
+                line: Some(12),
+                column: Some(5),
+                severity: Severity::Error,
+                code: Some(30015),
+                message: "undefined identifier 'foo'.".into(),
+                excerpt: Vec::new(),
+            }]
+        );
+    }
+
+    #[test]
+    fn header_without_location_or_code() {
+        let diagnostics = parse_diagnostics("internal error: something broke");
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].file, None);
+        assert_eq!(diagnostics[0].line, None);
+        assert_eq!(diagnostics[0].severity, Severity::Internal);
+        assert_eq!(diagnostics[0].code, None);
+        assert_eq!(diagnostics[0].message, "something broke");
+    }
+
+    #[test]
+    fn excerpt_and_caret_give_the_column() {
+        let log = "trace.slang(3): warning 41012: unused variable\n\
+                   \x20   float x = 1.0;\n\
+                   \x20         ^\n\
+                   \n\
+                   trace.slang(4): note: declared here\n";
+        let diagnostics = parse_diagnostics(log);
+        assert_eq!(diagnostics.len(), 2);
+        assert_eq!(diagnostics[0].severity, Severity::Warning);
+        assert_eq!(diagnostics[0].column, Some(11));
+        assert_eq!(
+            diagnostics[0].excerpt,
+            ["    float x = 1.0;", "          ^"]
+        );
+        assert_eq!(diagnostics[1].severity, Severity::Note);
+        assert!(diagnostics[1].excerpt.is_empty());
+    }
+
+    #[test]
+    fn lines_before_the_first_header_are_dropped() {
+        let diagnostics =
+            parse_diagnostics("compiling...\nshared.slang(1): fatal error: no such file");
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].severity, Severity::Error);
+    }
+
+    #[test]
+    fn display_reads_like_a_compiler() {
+        let diagnostic = Diagnostic {
+            file: Some("a.slang".into()),
+            line: Some(2),
+            column: Some(7),
+            severity: Severity::Error,
+            code: Some(1),
+            message: "bad".into(),
+            excerpt: vec!["x y".into()],
+        };
+        assert_eq!(diagnostic.to_string(), "a.slang:2:7: error 1: bad\n    x y");
+    }
+}
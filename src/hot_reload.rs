@@ -0,0 +1,126 @@
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    sync::mpsc::{self, Receiver},
+};
+
+use notify::{Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+
+use crate::{
+    compiler::ShaderProgram,
+    migration::{MigrationReport, migrate_values},
+    reflection::{BindingResources, Cursor, buffers_from_layout, resource_slots},
+};
+
+// Watches the shader search paths for edits to .slang files
+pub struct ShaderWatcher {
+    _watcher: RecommendedWatcher,
+    events: Receiver<notify::Result<Event>>,
+}
+
+impl ShaderWatcher {
+    pub fn new(paths: &[PathBuf]) -> notify::Result<Self> {
+        let (tx, events) = mpsc::channel();
+        let mut watcher = notify::recommended_watcher(tx)?;
+        for path in paths {
+            watcher.watch(path, RecursiveMode::Recursive)?;
+        }
+        Ok(ShaderWatcher {
+            _watcher: watcher,
+            events,
+        })
+    }
+
+    // Drains pending events. True if any .slang file was written, created or removed since the
+    // last call, so a save that touches the file several times still means one recompile.
+    pub fn changed(&self) -> bool {
+        let mut changed = false;
+        while let Ok(event) = self.events.try_recv() {
+            match event {
+                Ok(event) => {
+                    let relevant = matches!(
+                        event.kind,
+                        EventKind::Create(_) | EventKind::Modify(_) | EventKind::Remove(_)
+                    );
+                    if relevant && event.paths.iter().any(|p| is_slang_file(p)) {
+                        changed = true;
+                    }
+                }
+                Err(e) => eprintln!("Shader watcher error: {}", e),
+            }
+        }
+        changed
+    }
+}
+
+fn is_slang_file(path: &Path) -> bool {
+    path.extension().is_some_and(|ext| ext == "slang")
+}
+
+// Buffers are created fresh for the new layout, with the values that still fit copied over.
+// Textures move to wherever the field with the same path ended up, so they aren't uploaded
+// again. Samplers are cheap and get recreated on the next write, as do textures whose field
+// is new.
+pub fn migrate_binding_resources(
+    old_program: &ShaderProgram,
+    new_program: &ShaderProgram,
+    mut old_resources: BindingResources,
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+) -> (BindingResources, MigrationReport) {
+    let mut old_slots = HashMap::new();
+    resource_slots(
+        Cursor::fresh(old_program.global_type_layout()),
+        "",
+        &mut old_slots,
+    );
+    let mut new_slots = HashMap::new();
+    resource_slots(
+        Cursor::fresh(new_program.global_type_layout()),
+        "",
+        &mut new_slots,
+    );
+
+    let mut buffers = buffers_from_layout(device, new_program.layout_entries());
+    let report = migrate_values(
+        old_program.global_type_layout(),
+        new_program.global_type_layout(),
+        &old_resources.buffers,
+        &mut buffers,
+        device,
+        queue,
+    );
+
+    let mut resources = BindingResources {
+        buffers,
+        textures: HashMap::new(),
+        texture_views: HashMap::new(),
+        samplers: HashMap::new(),
+    };
+    for (path, (new_set, new_slot)) in &new_slots {
+        let Some((old_set, old_slot)) = old_slots.get(path) else {
+            continue;
+        };
+        let texture = old_resources
+            .textures
+            .get_mut(old_set)
+            .and_then(|set| set.remove(old_slot));
+        let texture_view = old_resources
+            .texture_views
+            .get_mut(old_set)
+            .and_then(|set| set.remove(old_slot));
+        if let (Some(texture), Some(texture_view)) = (texture, texture_view) {
+            resources
+                .textures
+                .entry(*new_set)
+                .or_default()
+                .insert(*new_slot, texture);
+            resources
+                .texture_views
+                .entry(*new_set)
+                .or_default()
+                .insert(*new_slot, texture_view);
+        }
+    }
+    (resources, report)
+}
@@ -1,6 +1,35 @@
 #![feature(generic_const_exprs)]
 
+// Lets the Writable derive name this crate as `::wgpu_tidy` from inside it too
+extern crate self as wgpu_tidy;
+
+pub mod bindgen;
+pub mod codegen;
+pub mod compiler;
+pub mod compute;
+pub mod diagnostics;
+pub mod hot_reload;
+pub mod migration;
 pub mod reflection;
+pub mod render_scale;
+pub mod sampler;
+pub mod shader_cache;
+pub mod skybox;
+pub mod texture;
+pub mod trace;
+pub mod tuning;
+pub mod validation;
+
+// Typed structs and bind groups for the shader globals, generated by build.rs
+#[allow(dead_code, non_snake_case, non_camel_case_types)]
+pub mod bindings {
+    pub mod gorilla {
+        include!(concat!(env!("OUT_DIR"), "/gorilla_bindings.rs"));
+    }
+}
+
+// Derived Writable impls refer to wgpu through this re-export
+pub use wgpu;
 
 use std::{
     collections::HashMap,
@@ -18,13 +47,19 @@ use encase::{
     ShaderType,
     internal::{BufferMut, WriteInto},
 };
-use image::{EncodableLayout, ImageError, RgbaImage};
-use proc_macros::Writable;
+use naga::valid::Capabilities;
+use codegen::{SlangModule, SlangType};
+use compiler::{ShaderBackend, ShaderCompiler, ShaderProgram};
+use hot_reload::{ShaderWatcher, migrate_binding_resources};
 use reflection::{
-    BindingResources, Cursor, Writable, base_layout_entries, bind_group_entries_from_layout,
-    buffers_from_layout,
+    BindingResources, Cursor, Writable, bind_group_entries_from_layout, buffers_from_layout,
 };
-use slang::Downcast;
+use render_scale::{RenderScale, ScaledView, UPSCALE_MODULE};
+use sampler::{Sampler, SamplerConfig};
+use skybox::RgbaSkybox;
+use texture::MipFilter;
+use trace::{BLIT_MODULE, TRACE_MODULE, Tile, TraceMode, TracedView};
+use tuning::Tuning;
 use wgpu::{*};
 use winit::{
     application::ApplicationHandler,
@@ -68,157 +103,6 @@ impl<T: ShaderType + WriteInto> GammaT for Gamma<T> {
     }
 }
 
-// Has to have equal resolution on all faces
-struct RgbaSkybox {
-    written_already: bool, // hack
-    px: RgbaImage,
-    nx: RgbaImage,
-    py: RgbaImage,
-    ny: RgbaImage,
-    pz: RgbaImage,
-    nz: RgbaImage,
-}
-
-impl RgbaSkybox {
-    fn dimensions(&self) -> (u32, u32) {
-        self.px.dimensions()
-    }
-    fn width(&self) -> u32 {
-        self.px.width()
-    }
-    fn height(&self) -> u32 {
-        self.px.height()
-    }
-    fn extent(&self) -> Extent3d {
-        Extent3d {
-            width: self.width(),
-            height: self.height(),
-            depth_or_array_layers: 6,
-        }
-    }
-    fn texture_format(&self) -> TextureFormat {
-        TextureFormat::Rgba8UnormSrgb
-    }
-    fn descriptor(&self) -> TextureDescriptor {
-        TextureDescriptor {
-            label: None,
-            size: self.extent(),
-            mip_level_count: 1,
-            sample_count: 1,
-            dimension: TextureDimension::D2,
-            format: self.texture_format(),
-            usage: TextureUsages::COPY_DST | TextureUsages::TEXTURE_BINDING,
-            view_formats: &[],
-        }
-    }
-    fn view_descriptor(&self) -> TextureViewDescriptor {
-        TextureViewDescriptor {
-            dimension: Some(TextureViewDimension::Cube),
-            ..Default::default()
-        }
-    }
-    fn as_bytevec(&self) -> Vec<u8> {
-        [
-            self.px.as_bytes(),
-            self.nx.as_bytes(),
-            self.py.as_bytes(),
-            self.ny.as_bytes(),
-            self.pz.as_bytes(),
-            self.nz.as_bytes(),
-        ]
-        .concat()
-    }
-    fn bytes_per_block(&self) -> u32 {
-        4
-    }
-    fn load_from_path(bg_path: &Path) -> Result<Self, ImageError> {
-        let [px, nx, py, ny, pz, nz]: [Result<_, ImageError>; 6] =
-            ["right", "left", "bottom", "top", "front", "back"]
-                .map(|x| {
-                    let mut im = image::open(bg_path.join(format!("{}.png", x)))?.into_rgba8();
-                    image::imageops::flip_vertical_in_place(&mut im);
-                    Ok(im)});
-        Ok(RgbaSkybox {
-            written_already: false,
-            px: px?,
-            nx: nx?,
-            py: py?,
-            ny: ny?,
-            pz: pz?,
-            nz: nz?,
-        })
-    }
-}
-
-impl Writable for RgbaSkybox {
-    fn write_at_cursor(
-        &self,
-        c: Cursor,
-        device: &wgpu::Device,
-        queue: &wgpu::Queue,
-        binding_resources: &mut BindingResources,
-    ) {
-        if self.written_already {return;}
-        let textures = &mut binding_resources.textures;
-        let texture_views = &mut binding_resources.texture_views;
-        let set_index = c.offset().set();
-        let slot_index = c.offset().slot();
-        if !textures.contains_key(&set_index) {
-            textures.insert(set_index, HashMap::new());
-            texture_views.insert(set_index, HashMap::new());
-        }
-        let tset = binding_resources.textures.get_mut(&set_index).unwrap();
-        let tviewset = binding_resources.texture_views.get_mut(&set_index).unwrap();
-        if !tset.contains_key(&slot_index) {
-            let texture = device.create_texture(&self.descriptor());
-            let texture_view = texture.create_view(&self.view_descriptor());
-            tset.insert(slot_index, texture);
-            tviewset.insert(slot_index, texture_view);
-        } else {
-            let curtex = tset.get_mut(&slot_index).unwrap();
-            if curtex.width() != self.width() || curtex.height() != self.height() {
-                let newtex = device.create_texture(&self.descriptor());
-                let newview = newtex.create_view(&self.view_descriptor());
-                tviewset.insert(slot_index, newview);
-                *curtex = newtex;
-            }
-        }
-        let tex = tset.get_mut(&slot_index).unwrap();
-        queue.write_texture(
-            TexelCopyTextureInfo {
-                texture: &tex,
-                mip_level: 0,
-                origin: Origin3d { x: 0, y: 0, z: 0 },
-                aspect: TextureAspect::All,
-            },
-            &self.as_bytevec(),
-            TexelCopyBufferLayout {
-                offset: 0,
-                bytes_per_row: Some(self.bytes_per_block() * self.width()),
-                rows_per_image: Some(self.height()),
-            },
-            self.extent(),
-        );
-    }
-}
-
-fn describe_skybox(skybox: &RgbaSkybox) -> TextureDescriptor {
-    TextureDescriptor {
-        label: None,
-        size: Extent3d {
-            width: skybox.width(),
-            height: skybox.height(),
-            depth_or_array_layers: 6,
-        },
-        mip_level_count: 1,
-        sample_count: 1,
-        dimension: TextureDimension::D2,
-        format: TextureFormat::Rgba8UnormSrgb,
-        usage: TextureUsages::COPY_DST | TextureUsages::TEXTURE_BINDING,
-        view_formats: &[],
-    }
-}
-
 struct FunnyBusiness<'a> {
     buffers: HashMap<(u32, u32), Buffer>,
     bind_group_entries: HashMap<(u32, u32), BindGroupEntry<'a>>,
@@ -254,7 +138,7 @@ impl GlobalShaderScope {
     }
 }
 
-#[derive(Writable)]
+#[derive(Clone, PartialEq, Writable, SlangType)]
 struct Camera {
     width: f32,
     height: f32,
@@ -378,6 +262,17 @@ impl CameraController {
 }
 
 struct ConstantBuffer<T>(T);
+
+impl<T: SlangType> SlangType for ConstantBuffer<T> {
+    fn slang_name() -> String {
+        format!("ConstantBuffer<{}>", T::slang_name())
+    }
+
+    fn declare(module: &mut SlangModule) {
+        T::declare(module);
+    }
+}
+
 impl<T: Writable> Writable for ConstantBuffer<T> {
     fn write_at_cursor(
         &self,
@@ -394,6 +289,16 @@ impl<T: Writable> Writable for ConstantBuffer<T> {
 
 struct StructuredBuffer<T>(Vec<T>);
 
+impl<T: SlangType> SlangType for StructuredBuffer<T> {
+    fn slang_name() -> String {
+        format!("StructuredBuffer<{}>", T::slang_name())
+    }
+
+    fn declare(module: &mut SlangModule) {
+        T::declare(module);
+    }
+}
+
 impl<T: Writable> Writable for StructuredBuffer<T> {
     fn write_at_cursor(
         &self,
@@ -412,10 +317,11 @@ impl<T: Writable> Writable for StructuredBuffer<T> {
             .unwrap()
             .get_mut(&c.offset().slot())
             .unwrap();
-        if data_buffer.size() != total_bytes_needed.try_into().unwrap() {
+        let total_bytes_needed: u64 = total_bytes_needed.try_into().unwrap();
+        if data_buffer.size() != total_bytes_needed {
             *data_buffer = device.create_buffer(&BufferDescriptor {
                 label: None,
-                size: total_bytes_needed.try_into().unwrap(),
+                size: total_bytes_needed,
                 usage: data_buffer.usage(),
                 mapped_at_creation: false,
             });
@@ -432,72 +338,54 @@ struct TR3 {
     v: Vector3<f32>,
 }
 
-#[derive(Debug, Writable)]
+#[derive(Debug, Writable, SlangType)]
+#[slang(implements = "IDifferentiable")]
 struct Hermite {
     pos: Vector3<f32>,
     normal: Vector3<f32>,
 }
 
+#[derive(Writable, SlangType)]
+#[slang(implements = "IDifferentiable")]
 struct SurfaceParams {
     support: f32,
     point_count: i32,
     point_data: StructuredBuffer<Hermite>,
+    #[slang(skip)]
+    #[writable(skip)]
     written_already: bool // hack
 }
 
-impl Writable for SurfaceParams {
-    fn write_at_cursor(
-        &self,
-        c: Cursor,
-        device: &wgpu::Device,
-        queue: &wgpu::Queue,
-        binding_resources: &mut BindingResources,
-    ) {
-        if self.written_already {return;}
-        let support_cursor = c.navigate_field(0).unwrap();
-        let point_count_cursor = c.navigate_field(1).unwrap();
-        let point_data_cursor = c.navigate_field(2).unwrap();
-        self.support.write_at_cursor(support_cursor, device, queue, binding_resources);
-        self.point_count.write_at_cursor(point_count_cursor, device, queue, binding_resources);
-        self.point_data.write_at_cursor(point_data_cursor, device, queue, binding_resources);
+// The points are too many to upload every frame, so they only go out when they changed
+fn write_surface(
+    surface: &ConstantBuffer<SurfaceParams>,
+    c: Cursor,
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    binding_resources: &mut BindingResources,
+) {
+    if !surface.0.written_already {
+        surface.write_at_cursor(c, device, queue, binding_resources);
     }
 }
 
-struct DummySampler {
-    written_already: bool // hack
-}
-
-impl Writable for DummySampler {
-    fn write_at_cursor(
-        &self,
-        c: Cursor,
-        device: &wgpu::Device,
-        queue: &wgpu::Queue,
-        binding_resources: &mut BindingResources,
-    ) {
-        if self.written_already {return;}
-        let set_index = c.offset().set();
-        let slot_index = c.offset().slot();
-        if !binding_resources.samplers.contains_key(&set_index) {
-            binding_resources.samplers.insert(set_index, HashMap::new());
-        }
-        let set = binding_resources.samplers.get_mut(&set_index).unwrap();
-        if !set.contains_key(&slot_index) {
-            set.insert(
-                slot_index,
-                device.create_sampler(&SamplerDescriptor::default()),
-            );
-        }
-    }
+// The types the shaders `import shared` for. src/shader/shared.slang is checked in, since
+// build.rs compiles gorilla.slang against it; `wgpu_tidy codegen` regenerates it.
+pub fn shared_module() -> SlangModule {
+    SlangModule::new("shared")
+        .with::<Camera>()
+        .with::<SurfaceParams>()
+        .with::<Tile>()
 }
 
 #[derive(Writable)]
 struct GraphicsGlobal {
     camera: ConstantBuffer<Camera>,
+    #[writable(with = write_surface)]
     surface: ConstantBuffer<SurfaceParams>,
     background0: ConstantBuffer<RgbaSkybox>,
     // background1: ConstantBuffer<RgbaSkybox>,
-    background_sampler: ConstantBuffer<DummySampler>,
+    background_sampler: ConstantBuffer<Sampler>,
 }
 
 trait ToBytes {
@@ -588,6 +476,31 @@ impl Writable for i32 {
     }
 }
 
+impl Writable for u32 {
+    fn write_at_cursor(
+        &self,
+        c: reflection::Cursor,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        binding_resources: &mut BindingResources,
+    ) {
+        let bind_group_ix = c.offset().set();
+        let bind_slot_ix = c.offset().slot();
+        let uniform_offset = c.offset().uniform();
+        let buffer = binding_resources
+            .buffers
+            .get(&bind_group_ix)
+            .unwrap()
+            .get(&bind_slot_ix)
+            .unwrap();
+        queue.write_buffer(
+            buffer,
+            uniform_offset.try_into().unwrap(),
+            &self.to_le_bytes(),
+        );
+    }
+}
+
 impl Writable for Vector3<f32> {
     // Technically an indexing operation, so can panic on wrong offsets
     fn write_at_cursor(
@@ -649,6 +562,12 @@ impl Writable for Matrix3<f32> {
 //     }
 // }
 
+const SHADER_MODULE: &str = "gorilla.slang";
+
+// What the device is requested with, and what `wgpu-tidy validate` checks shaders against
+pub const REQUIRED_FEATURES: wgpu::Features =
+    wgpu::Features::TEXTURE_ADAPTER_SPECIFIC_FORMAT_FEATURES;
+
 pub enum AppState<'a> {
     Uninitialized(),
     Initialized(App<'a>),
@@ -664,11 +583,21 @@ pub struct App<'a> {
     render_pipeline: RenderPipeline,
     binding_resources: BindingResources,
     bind_group_layouts: HashMap<usize, BindGroupLayout>,
-    layout_entries: HashMap<usize, Vec<BindGroupLayoutEntry>>,
     graphics_global: GraphicsGlobal,
-    program: slang::ComponentType,
-    slang_global_session: slang::GlobalSession,
-    slang_session: slang::Session,
+    shader_compiler: ShaderCompiler,
+    program: ShaderProgram,
+    shader_watcher: Option<ShaderWatcher>,
+    // What naga accepts on this device
+    capabilities: Capabilities,
+    tuning: Tuning,
+    // Set when drawing through the compute path
+    traced: Option<TracedView>,
+    // What the traced image so far was traced with
+    traced_camera: Option<Camera>,
+    // Either path draws at `render_scale` of the window size into `scaled`
+    render_scale: RenderScale,
+    scaled: ScaledView,
+    last_frame: Option<Instant>,
     fixed_time: Instant,
     mouse_capture_mode: CursorGrabMode,
     cursor_is_visible: bool,
@@ -701,12 +630,251 @@ fn sphere(m: usize, n: usize) -> Vec<Hermite> {
     out
 }
 
+// Rebuilt from scratch whenever the shaders are recompiled
+fn create_render_pipeline(
+    device: &Device,
+    program: &ShaderProgram,
+    surface_format: TextureFormat,
+    tuning: &Tuning,
+) -> (RenderPipeline, HashMap<usize, BindGroupLayout>) {
+    // Using Slang-compiled code
+    let shader_module = program.create_shader_module(device);
+    let constants = tuning.pipeline_constants(program);
+
+    let bind_group_layouts = program.create_bind_group_layouts(device);
+
+    // Pipeline layout slots have to follow the set indices
+    let mut bind_group_layouts_sorted: Vec<(&usize, &BindGroupLayout)> =
+        bind_group_layouts.iter().collect();
+    bind_group_layouts_sorted.sort_by_key(|(k, _)| **k);
+    let bind_group_layouts_vec: Vec<&BindGroupLayout> =
+        bind_group_layouts_sorted.into_iter().map(|(_, v)| v).collect();
+
+    let render_pipeline_layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
+        label: Some("render_pipeline_layout"),
+        bind_group_layouts: &bind_group_layouts_vec,
+        push_constant_ranges: &[],
+    });
+
+    let render_pipeline = device.create_render_pipeline(&RenderPipelineDescriptor {
+        label: Some("render_pipeline"),
+        layout: Some(&render_pipeline_layout),
+        vertex: VertexState {
+            module: &shader_module,
+            entry_point: Some("vertex"),
+            compilation_options: Default::default(),
+            buffers: &[],
+        },
+        primitive: PrimitiveState {
+            topology: PrimitiveTopology::TriangleList,
+            strip_index_format: None,
+            front_face: FrontFace::Ccw,
+            cull_mode: Some(Face::Back),
+            unclipped_depth: false,
+            polygon_mode: PolygonMode::Fill,
+            conservative: false,
+        },
+        depth_stencil: None,
+        multisample: MultisampleState {
+            count: 1,
+            mask: !0,
+            alpha_to_coverage_enabled: false,
+        },
+        fragment: Some(FragmentState {
+            module: &shader_module,
+            entry_point: Some("fragment"),
+            compilation_options: PipelineCompilationOptions {
+                constants: &constants,
+                ..Default::default()
+            },
+            targets: &[Some(ColorTargetState {
+                format: surface_format,
+                blend: None,
+                write_mask: ColorWrites::ALL,
+            })],
+        }),
+        multiview: None,
+        cache: None,
+    });
+
+    (render_pipeline, bind_group_layouts)
+}
+
 impl<'a> App<'a> {
+    // Keeps the current pipeline running if the new code doesn't compile
+    fn reload_shaders(&mut self) {
+        let program = match self.shader_compiler.compile(SHADER_MODULE) {
+            Ok(program) => program,
+            Err(e) => {
+                eprintln!("{}", e);
+                return;
+            }
+        };
+        if let Err(e) = validation::validate(&program, self.capabilities) {
+            eprintln!("{}", e);
+            return;
+        }
+        // wgpu panics on invalid shaders unless the error is caught in a scope
+        self.device.push_error_scope(ErrorFilter::Validation);
+        let (render_pipeline, bind_group_layouts) = create_render_pipeline(
+            &self.device,
+            &program,
+            self.surface_config.format,
+            &self.tuning,
+        );
+        if let Some(e) = pollster::block_on(self.device.pop_error_scope()) {
+            eprintln!("{}", e);
+            return;
+        }
+        let old_resources = std::mem::replace(
+            &mut self.binding_resources,
+            BindingResources {
+                buffers: HashMap::new(),
+                textures: HashMap::new(),
+                texture_views: HashMap::new(),
+                samplers: HashMap::new(),
+            },
+        );
+        let (binding_resources, report) = migrate_binding_resources(
+            &self.program,
+            &program,
+            old_resources,
+            &self.device,
+            &self.queue,
+        );
+        self.binding_resources = binding_resources;
+        self.program = program;
+        self.render_pipeline = render_pipeline;
+        self.bind_group_layouts = bind_group_layouts;
+        println!(
+            "Reloaded {}, kept {} values",
+            SHADER_MODULE,
+            report.preserved.len()
+        );
+        if !report.dropped.is_empty() {
+            println!("  dropped: {}", report.dropped.join(", "));
+        }
+        if !report.zeroed.is_empty() {
+            println!("  zeroed: {}", report.zeroed.join(", "));
+        }
+        // Values the shader lost have to come from the Rust side again
+        if !report.is_lossless() {
+            self.graphics_global.surface.0.written_already = false;
+        }
+        if self.traced.is_some() {
+            self.reload_traced_view();
+        }
+        self.reload_scaled_view();
+    }
+
+    // Keeps the old view if anything fails
+    fn reload_scaled_view(&mut self) {
+        let (width, height) = self.scaled.size();
+        self.device.push_error_scope(ErrorFilter::Validation);
+        let scaled = ScaledView::new(
+            &self.device,
+            &self.shader_compiler,
+            self.capabilities,
+            self.surface_config.format,
+            &self.tuning,
+            width,
+            height,
+        );
+        let scope_error = pollster::block_on(self.device.pop_error_scope());
+        match (scaled, scope_error) {
+            (Ok(scaled), None) => {
+                self.scaled = scaled;
+                println!("Reloaded {}", UPSCALE_MODULE);
+            }
+            (Err(e), _) => eprintln!("{}", e),
+            (_, Some(e)) => eprintln!("{}", e),
+        }
+    }
+
+    // The camera follows the internal resolution, so the image still covers the whole view
+    fn update_render_size(&mut self) {
+        let (width, height) = self
+            .render_scale
+            .scaled_size(self.size.width, self.size.height);
+        self.scaled.resize(width, height);
+        if let Some(traced) = &mut self.traced {
+            traced.resize(width, height);
+        }
+        self.graphics_global.camera.0.width = width as f32;
+        self.graphics_global.camera.0.height = height as f32;
+    }
+
+    // Starts over with fresh resources, keeping the old view if anything fails
+    fn reload_traced_view(&mut self) {
+        let Some(mode) = self.traced.as_ref().map(TracedView::mode) else {
+            return;
+        };
+        self.device.push_error_scope(ErrorFilter::Validation);
+        let traced = TracedView::new(
+            &self.device,
+            &self.shader_compiler,
+            self.capabilities,
+            self.surface_config.format,
+            &self.tuning,
+            mode,
+            self.scaled.size(),
+        );
+        let scope_error = pollster::block_on(self.device.pop_error_scope());
+        match (traced, scope_error) {
+            (Ok(traced), None) => {
+                self.traced = Some(traced);
+                self.graphics_global.surface.0.written_already = false;
+                println!("Reloaded {} and {}", TRACE_MODULE, BLIT_MODULE);
+            }
+            (Err(e), _) => eprintln!("{}", e),
+            (_, Some(e)) => eprintln!("{}", e),
+        }
+    }
+
+    fn render_traced(&mut self) -> Result<(), SurfaceError> {
+        let output = self.surface.get_current_texture()?;
+        let view = output
+            .texture
+            .create_view(&wgpu::TextureViewDescriptor::default());
+        let scaled_view = self.scaled.target_view(&self.device);
+        if let Some(traced) = &mut self.traced {
+            // A moved camera or a surface about to be rewritten makes the traced tiles stale
+            let camera = &self.graphics_global.camera.0;
+            if self.traced_camera.as_ref() != Some(camera)
+                || !self.graphics_global.surface.0.written_already
+            {
+                traced.restart();
+                self.traced_camera = Some(camera.clone());
+            }
+            traced.render(&self.device, &self.queue, &self.graphics_global, &scaled_view);
+        }
+        self.graphics_global.surface.0.written_already = true;
+        self.scaled.upscale(&self.device, &self.queue, &view);
+        output.present();
+        Ok(())
+    }
+
     fn render(&mut self) -> Result<(), SurfaceError> {
+        if self.shader_watcher.as_ref().is_some_and(|w| w.changed()) {
+            self.reload_shaders();
+        }
+
+        // Render to render, so waiting on the GPU for the next surface texture counts too
+        let now = Instant::now();
+        if let Some(last_frame) = self.last_frame.replace(now) {
+            // Progressive frames trace a fixed number of tiles, so their times don't depend
+            // on the scale and it stays where it is
+            if self.traced.as_ref().is_none_or(|t| t.mode() == TraceMode::Full) {
+                self.render_scale.record_frame(now - last_frame);
+            }
+        }
+        self.update_render_size();
+        if self.traced.is_some() {
+            return self.render_traced();
+        }
+
         // Global graphics object?
-        let linked_program = self.program.link().unwrap();
-        let reflection = linked_program.layout(0).unwrap();
-        let global_type_layout = reflection.global_params_type_layout();
+        let global_type_layout = self.program.global_type_layout();
 
         let top_cursor = Cursor::fresh(global_type_layout);
         self.graphics_global.write_at_cursor(
@@ -716,13 +884,11 @@ impl<'a> App<'a> {
             &mut self.binding_resources,
         );
         self.graphics_global.surface.0.written_already = true;
-        self.graphics_global.background0.0.written_already = true;
         // self.graphics_global.background1.0.written_already = true;
-        self.graphics_global.background_sampler.0.written_already = true;
 
         // Make bind groups
         let bind_group_entries =
-            bind_group_entries_from_layout(&self.layout_entries, &self.binding_resources);
+            bind_group_entries_from_layout(self.program.layout_entries(), &self.binding_resources);
         let bind_group_labels: HashMap<usize, String> = bind_group_entries
             .iter()
             .map(|(&k, v)| (k, format!("bg{}", k)))
@@ -745,6 +911,7 @@ impl<'a> App<'a> {
         let view = output
             .texture
             .create_view(&wgpu::TextureViewDescriptor::default());
+        let scaled_view = self.scaled.target_view(&self.device);
         let mut encoder = self
             .device
             .create_command_encoder(&wgpu::CommandEncoderDescriptor {
@@ -754,7 +921,7 @@ impl<'a> App<'a> {
             let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
                 label: Some("render_pass"),
                 color_attachments: &[Some(wgpu::RenderPassColorAttachment {
-                    view: &view,
+                    view: &scaled_view,
                     resolve_target: None,
                     ops: wgpu::Operations {
                         load: wgpu::LoadOp::Clear(wgpu::Color {
@@ -777,6 +944,7 @@ impl<'a> App<'a> {
             render_pass.draw(0..3, 0..1);
         }
         self.queue.submit(std::iter::once(encoder.finish()));
+        self.scaled.upscale(&self.device, &self.queue, &view);
         output.present();
         Ok(())
     }
@@ -788,6 +956,7 @@ impl<'a> App<'a> {
             self.surface_config.width = new_size.width;
             self.surface_config.height = new_size.height;
             self.surface.configure(&self.device, &self.surface_config);
+            self.update_render_size();
         }
     }
     fn toggle_mouse_capture(&mut self) {
@@ -931,7 +1100,7 @@ impl<'a> ApplicationHandler for AppState<'a> {
         let device_future = adapter.request_device(
             &wgpu::DeviceDescriptor {
                 label: Some("device"),
-                required_features: wgpu::Features::TEXTURE_ADAPTER_SPECIFIC_FORMAT_FEATURES,
+                required_features: REQUIRED_FEATURES,
                 required_limits: wgpu::Limits {
                     max_bind_groups: 5,
                     ..Default::default()
@@ -941,133 +1110,141 @@ impl<'a> ApplicationHandler for AppState<'a> {
             None,
         );
         let (device, queue) = pollster::block_on(device_future).unwrap();
+        let capabilities = validation::capabilities(
+            device.features(),
+            adapter.get_downlevel_capabilities().flags,
+        );
 
         surface.configure(&device, &surface_config); // causes segfault if device, surface_config die.
 
         // Slang block
-        let global_session = slang::GlobalSession::new().unwrap();
-        let search_path = std::ffi::CString::new("src/shader").unwrap();
-
-        let session_options = slang::CompilerOptions::default().matrix_layout_row(true);
-
-        let target_desc = slang::TargetDesc::default()
-            .format(slang::CompileTarget::Wgsl)
-            .profile(global_session.find_profile("glsl_450"));
-
-        let targets = [target_desc];
-        let search_paths = [search_path.as_ptr()];
-
-        let session_desc = slang::SessionDesc::default()
-            .targets(&targets)
-            .search_paths(&search_paths)
-            .options(&session_options);
-
-        let session = global_session.create_session(&session_desc).unwrap();
-
-        let module = session.load_module("gorilla.slang").unwrap();
-
-        let frag_entry_point = module.find_entry_point_by_name("fragment").unwrap();
-        let vertex_entry_point = module.find_entry_point_by_name("vertex").unwrap();
-
-        let program = session
-            .create_composite_component_type(&[
-                module.downcast().clone(),
-                frag_entry_point.downcast().clone(),
-                vertex_entry_point.downcast().clone(),
-            ])
-            .unwrap();
-
-        let linked_program = program.link().unwrap();
-        let shader_bytecode = linked_program.target_code(0).unwrap();
-
-        let reflection = linked_program.layout(0).unwrap();
-        let global_type_layout = reflection.global_params_type_layout();
+        // SHADER_BACKEND=spirv compiles through SPIR-V instead of WGSL
+        let backend = std::env::var("SHADER_BACKEND")
+            .ok()
+            .and_then(|name| ShaderBackend::from_name(&name))
+            .unwrap_or(ShaderBackend::Wgsl);
+        let tuning = Tuning::default();
+        let shader_compiler = match ShaderCompiler::new() {
+            Ok(compiler) => tuning.apply_defines(
+                compiler
+                    .backend(backend)
+                    .search_path("src/shader")
+                    .cache_dir("target/shader_cache")
+                    .all_entry_points(),
+            ),
+            Err(e) => {
+                eprintln!("{}", e);
+                event_loop.exit();
+                return;
+            }
+        };
+        let program = match shader_compiler.compile(SHADER_MODULE) {
+            Ok(program) => program,
+            Err(e) => {
+                eprintln!("{}", e);
+                event_loop.exit();
+                return;
+            }
+        };
+        if let Err(e) = validation::validate(&program, capabilities) {
+            eprintln!("{}", e);
+            event_loop.exit();
+            return;
+        }
 
-        let layout_entries = base_layout_entries(global_type_layout);
-        let buffers = buffers_from_layout(&device, &layout_entries);
-        println!("{}", shader_bytecode.as_str().unwrap());
+        // Without a watcher the app still runs, just without hot reloading
+        let shader_watcher = match ShaderWatcher::new(shader_compiler.search_paths()) {
+            Ok(watcher) => Some(watcher),
+            Err(e) => {
+                eprintln!("Failed to watch shaders: {}", e);
+                None
+            }
+        };
 
-        // Using Slang-compiled code
-        let shader_module = device.create_shader_module(ShaderModuleDescriptor {
-            label: None,
-            source: ShaderSource::Wgsl(std::borrow::Cow::Borrowed(
-                shader_bytecode.as_str().unwrap(),
-            )),
-        });
+        let buffers = buffers_from_layout(&device, program.layout_entries());
+        if program.from_cache() {
+            println!("Loaded {} from the shader cache", SHADER_MODULE);
+        }
+        if let Some(code) = program.code_str() {
+            println!("{}", code);
+        }
 
-        let bind_group_layout_descriptors: HashMap<usize, BindGroupLayoutDescriptor> =
-            layout_entries
-                .iter()
-                .map(|(&k, v)| {
-                    (
-                        k,
-                        BindGroupLayoutDescriptor {
-                            label: None,
-                            entries: v,
-                        },
-                    )
-                })
-                .collect();
-
-        let bind_group_layouts: HashMap<usize, BindGroupLayout> = bind_group_layout_descriptors
-            .iter()
-            .map(|(&k, d)| (k, device.create_bind_group_layout(d)))
-            .collect();
+        device.push_error_scope(ErrorFilter::Validation);
+        let (render_pipeline, bind_group_layouts) =
+            create_render_pipeline(&device, &program, surface_format, &tuning);
+        if let Some(e) = pollster::block_on(device.pop_error_scope()) {
+            eprintln!("{}", e);
+            event_loop.exit();
+            return;
+        }
 
-        let bind_group_layouts_vec: Vec<&BindGroupLayout> =
-            bind_group_layouts.iter().map(|(_, v)| v).collect();
-        // let bge = BindGroupEntry {
-        //     binding: 0,
-        //     resource: BindingResource::Buffer(BufferBinding {
-        //         buffer: (),
-        //         offset: (),
-        //         size: (),
-        //     }),
-        // };
-
-        let render_pipeline_layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
-            label: Some("render_pipeline_layout"),
-            bind_group_layouts: &bind_group_layouts_vec,
-            push_constant_ranges: &[],
-        });
+        // RENDER_PATH=compute traces the view in compute tiles instead of in `fragment`,
+        // RENDER_PATH=progressive spreads the tiles over several frames
+        let trace_mode = match std::env::var("RENDER_PATH").as_deref() {
+            Ok("compute") => Some(TraceMode::Full),
+            Ok("progressive") => Some(TraceMode::Progressive),
+            _ => None,
+        };
+        let traced = if let Some(mode) = trace_mode {
+            device.push_error_scope(ErrorFilter::Validation);
+            let traced = TracedView::new(
+                &device,
+                &shader_compiler,
+                capabilities,
+                surface_format,
+                &tuning,
+                mode,
+                (size.width, size.height),
+            );
+            let scope_error = pollster::block_on(device.pop_error_scope());
+            match (traced, scope_error) {
+                (Ok(traced), None) => Some(traced),
+                (Err(e), _) => {
+                    eprintln!("{}", e);
+                    event_loop.exit();
+                    return;
+                }
+                (_, Some(e)) => {
+                    eprintln!("{}", e);
+                    event_loop.exit();
+                    return;
+                }
+            }
+        } else {
+            None
+        };
 
-        let render_pipeline = device.create_render_pipeline(&RenderPipelineDescriptor {
-            label: Some("render_pipeline"),
-            layout: Some(&render_pipeline_layout),
-            vertex: VertexState {
-                module: &shader_module,
-                entry_point: Some("vertex"),
-                compilation_options: Default::default(),
-                buffers: &[],
-            },
-            primitive: PrimitiveState {
-                topology: PrimitiveTopology::TriangleList,
-                strip_index_format: None,
-                front_face: FrontFace::Ccw,
-                cull_mode: Some(Face::Back),
-                unclipped_depth: false,
-                polygon_mode: PolygonMode::Fill,
-                conservative: false,
-            },
-            depth_stencil: None,
-            multisample: MultisampleState {
-                count: 1,
-                mask: !0,
-                alpha_to_coverage_enabled: false,
-            },
-            fragment: Some(FragmentState {
-                module: &shader_module,
-                entry_point: Some("fragment"),
-                compilation_options: PipelineCompilationOptions::default(),
-                targets: &[Some(ColorTargetState {
-                    format: surface_format,
-                    blend: None,
-                    write_mask: ColorWrites::ALL,
-                })],
-            }),
-            multiview: None,
-            cache: None,
-        });
+        // TARGET_FPS is the frame rate the render scale aims for
+        let target_fps = std::env::var("TARGET_FPS")
+            .ok()
+            .and_then(|fps| fps.parse::<f32>().ok())
+            .filter(|fps| *fps > 0.0)
+            .unwrap_or(60.0);
+        let render_scale = RenderScale::new(Duration::from_secs_f32(1.0 / target_fps));
+        device.push_error_scope(ErrorFilter::Validation);
+        let scaled = ScaledView::new(
+            &device,
+            &shader_compiler,
+            capabilities,
+            surface_format,
+            &tuning,
+            size.width,
+            size.height,
+        );
+        let scope_error = pollster::block_on(device.pop_error_scope());
+        let scaled = match (scaled, scope_error) {
+            (Ok(scaled), None) => scaled,
+            (Err(e), _) => {
+                eprintln!("{}", e);
+                event_loop.exit();
+                return;
+            }
+            (_, Some(e)) => {
+                eprintln!("{}", e);
+                event_loop.exit();
+                return;
+            }
+        };
 
         let camera = Camera {
             width: size.width as f32,
@@ -1086,9 +1263,19 @@ impl<'a> ApplicationHandler for AppState<'a> {
             written_already: false,
         };
 
-        let background0 = RgbaSkybox::load_from_path(Path::new("textures/bg1")).unwrap();
-        let background1 = RgbaSkybox::load_from_path(Path::new("textures/bg_debug")).unwrap();
-        let background_sampler = DummySampler {written_already:false};
+        let load_skybox = |path: &str| {
+            RgbaSkybox::load_from_path(Path::new(path)).map(|x| x.with_mips(MipFilter::Lanczos))
+        };
+        let (background0, background1) =
+            match (load_skybox("textures/bg1"), load_skybox("textures/bg_debug")) {
+                (Ok(bg0), Ok(bg1)) => (bg0, bg1),
+                (Err(e), _) | (_, Err(e)) => {
+                    eprintln!("Failed to load skybox: {}", e);
+                    event_loop.exit();
+                    return;
+                }
+            };
+        let background_sampler = Sampler::new(SamplerConfig::trilinear()).unwrap();
 
         let graphics_global = GraphicsGlobal {
             camera: ConstantBuffer(camera),
@@ -1127,11 +1314,17 @@ impl<'a> ApplicationHandler for AppState<'a> {
             render_pipeline,
             bind_group_layouts,
             binding_resources,
-            layout_entries,
             graphics_global,
+            shader_compiler,
             program,
-            slang_global_session: global_session,
-            slang_session: session,
+            tuning,
+            traced,
+            traced_camera: None,
+            render_scale,
+            scaled,
+            last_frame: None,
+            shader_watcher,
+            capabilities,
             fixed_time,
             mouse_capture_mode,
             cursor_is_visible,
@@ -1171,3 +1364,33 @@ impl<'a> ApplicationHandler for AppState<'a> {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn shared_slang_is_current() {
+        assert_eq!(
+            shared_module().source(),
+            include_str!("shader/shared.slang"),
+            "src/shader/shared.slang is stale, regenerate it with `cargo run -- codegen`"
+        );
+    }
+
+    // bindgen.rs finds the bindings by walking fields, so check it against what the app binds
+    #[test]
+    fn generated_bindings_match_reflection() {
+        let compiler = Tuning::default().apply_defines(
+            ShaderCompiler::new()
+                .unwrap()
+                .search_path(concat!(env!("CARGO_MANIFEST_DIR"), "/src/shader"))
+                .all_entry_points(),
+        );
+        let program = compiler.compile(SHADER_MODULE).unwrap();
+        assert_eq!(
+            program.layout_entries(),
+            &bindings::gorilla::layout_entries()
+        );
+    }
+}
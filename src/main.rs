@@ -1,94 +1,225 @@
-use std::{collections::HashMap, io::Write, sync::Arc};
-
-use slang::Downcast;
-use wgpu::include_wgsl;
-use wgpu_tidy::{reflection::{layout_entries_wowee, walk_him_down, Cursor}, AppState};
+use std::{
+    path::{Path, PathBuf},
+    process::ExitCode,
+};
+
+use clap::{Args, Parser, Subcommand};
+use wgpu_tidy::{
+    AppState,
+    compiler::{ShaderBackend, ShaderCompiler, ShaderProgram},
+    reflection::{Cursor, parameter_tree},
+    validation,
+};
 use winit::event_loop::{self, EventLoop};
 
-fn just_validate_wgsl() {
-
-
-    let instance = wgpu::Instance::new(&wgpu::InstanceDescriptor {
-        backends: wgpu::Backends::VULKAN | wgpu::Backends::METAL,
-        ..Default::default()
-    });
-
-    let adapter_future = instance.request_adapter(&wgpu::RequestAdapterOptions {
-        power_preference: wgpu::PowerPreference::HighPerformance,
-        force_fallback_adapter: false,
-        compatible_surface: None,
-    });
-    let adapter = pollster::block_on(adapter_future).unwrap();
-
-    let device_future = adapter.request_device(
-        &wgpu::DeviceDescriptor {
-            label: Some("device"),
-            required_features: wgpu::Features::TEXTURE_ADAPTER_SPECIFIC_FORMAT_FEATURES,
-            required_limits: wgpu::Limits {
-                max_bind_groups: 5,
-                ..Default::default()
-            },
-            memory_hints: wgpu::MemoryHints::default(),
-        },
-        None,
-    );
-    let (device, queue) = pollster::block_on(device_future).unwrap();
-    let shader_module = device.create_shader_module(include_wgsl!("shader/testy5.wgsl"));
+// Everything except `run` works without a GPU, so shaders can be checked on CI or a headless box.
+#[derive(Parser)]
+#[command(
+    name = "wgpu_tidy",
+    about = "Build and inspect the Slang shaders offline"
+)]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
 }
 
-fn main() {
-    just_validate_wgsl();
-    // let event_loop = EventLoop::new().unwrap();
-
-    // event_loop.set_control_flow(event_loop::ControlFlow::Poll);
-
-    // let mut app_state = AppState::Uninitialized();
-    // event_loop.run_app(&mut app_state).unwrap();
-
-    // let global_session = slang::GlobalSession::new().unwrap();
-    // let search_path = std::ffi::CString::new("src/shader").unwrap();
+#[derive(Subcommand)]
+enum Command {
+    /// Write the generated target code (WGSL by default)
+    Compile {
+        #[command(flatten)]
+        shader: ShaderArgs,
+        /// Defaults to the module name with a .wgsl or .spv extension
+        #[arg(short, long)]
+        out: Option<PathBuf>,
+    },
+    /// Dump the reflected parameter tree as JSON
+    Reflect {
+        #[command(flatten)]
+        shader: ShaderArgs,
+        /// Defaults to stdout
+        #[arg(short, long)]
+        out: Option<PathBuf>,
+    },
+    /// Print the bind group layout entries derived from reflection
+    Layouts {
+        #[command(flatten)]
+        shader: ShaderArgs,
+    },
+    /// Run naga validation over the generated code
+    Validate {
+        #[command(flatten)]
+        shader: ShaderArgs,
+    },
+    /// Regenerate shared.slang from the Rust types the shaders import
+    Codegen {
+        /// Directory to write shared.slang to
+        #[arg(default_value = "src/shader")]
+        dir: PathBuf,
+    },
+    /// Open the viewer window
+    Run,
+}
 
-    // let session_options = slang::CompilerOptions::default().matrix_layout_row(true);
+#[derive(Args)]
+struct ShaderArgs {
+    /// Path to the .slang module; its directory is searched for imports
+    module: PathBuf,
+    /// Additional directories to search for imported modules
+    #[arg(short = 'I', long = "include")]
+    includes: Vec<PathBuf>,
+    /// Entry points to link; every entry point the module defines if none are given
+    #[arg(short, long = "entry")]
+    entry_points: Vec<String>,
+    /// wgsl or spirv
+    #[arg(short, long, default_value = "wgsl", value_parser = parse_backend)]
+    backend: ShaderBackend,
+    /// Preprocessor definitions, NAME or NAME=VALUE
+    #[arg(short = 'D', long = "define")]
+    defines: Vec<String>,
+    /// Reuse and store emitted code in this directory
+    #[arg(long)]
+    cache: Option<PathBuf>,
+}
 
-    // let target_desc = slang::TargetDesc::default()
-    //     .format(slang::CompileTarget::Wgsl)
-    //     .profile(global_session.find_profile("glsl_450"));
+fn parse_backend(name: &str) -> Result<ShaderBackend, String> {
+    ShaderBackend::from_name(name).ok_or_else(|| format!("unknown backend `{}`", name))
+}
 
-    // let targets = [target_desc];
-    // let search_paths = [search_path.as_ptr()];
+impl ShaderArgs {
+    fn compile(&self) -> Result<ShaderProgram, String> {
+        let search_dir = self
+            .module
+            .parent()
+            .filter(|p| !p.as_os_str().is_empty())
+            .unwrap_or(Path::new("."));
+        let module_name = self
+            .module
+            .file_name()
+            .and_then(|n| n.to_str())
+            .ok_or_else(|| format!("{} is not a module path", self.module.display()))?;
+
+        let mut compiler = ShaderCompiler::new()
+            .map_err(|e| e.to_string())?
+            .backend(self.backend)
+            .search_path(search_dir);
+        for include in &self.includes {
+            compiler = compiler.search_path(include);
+        }
+        for define in &self.defines {
+            let (name, value) = define.split_once('=').unwrap_or((define, "1"));
+            compiler = compiler.define(name, value);
+        }
+        for entry_point in &self.entry_points {
+            compiler = compiler.entry_point(entry_point);
+        }
+        if self.entry_points.is_empty() {
+            compiler = compiler.all_entry_points();
+        }
+        if let Some(cache) = &self.cache {
+            compiler = compiler.cache_dir(cache);
+        }
+        compiler.compile(module_name).map_err(|e| e.to_string())
+    }
+}
 
-    // let session_desc = slang::SessionDesc::default()
-    //     .targets(&targets)
-    //     .search_paths(&search_paths)
-    //     .options(&session_options);
+fn write_output(out: Option<&Path>, contents: &[u8]) -> Result<(), String> {
+    match out {
+        Some(path) => std::fs::write(path, contents)
+            .map_err(|e| format!("failed to write {}: {}", path.display(), e)),
+        None => {
+            use std::io::Write;
+            std::io::stdout()
+                .write_all(contents)
+                .map_err(|e| e.to_string())
+        }
+    }
+}
 
-    // let session = global_session.create_session(&session_desc).unwrap();
+fn compile(shader: &ShaderArgs, out: Option<PathBuf>) -> Result<(), String> {
+    let program = shader.compile()?;
+    let out = out.unwrap_or_else(|| {
+        shader.module.with_extension(match shader.backend {
+            ShaderBackend::Wgsl => "wgsl",
+            ShaderBackend::SpirV => "spv",
+        })
+    });
+    write_output(Some(out.as_path()), program.code())?;
+    println!("Wrote {}", out.display());
+    Ok(())
+}
 
-    // let module = session.load_module("gorilla.slang").unwrap();
+fn reflect(shader: &ShaderArgs, out: Option<PathBuf>) -> Result<(), String> {
+    let program = shader.compile()?;
+    let tree = parameter_tree("", Cursor::fresh(program.global_type_layout()));
+    let json = serde_json::to_string_pretty(&tree).map_err(|e| e.to_string())?;
+    write_output(out.as_deref(), format!("{}\n", json).as_bytes())
+}
 
-    // let frag_entry_point = module.find_entry_point_by_name("fragment").unwrap();
+fn layouts(shader: &ShaderArgs) -> Result<(), String> {
+    let program = shader.compile()?;
+    let mut sets: Vec<_> = program.layout_entries().iter().collect();
+    sets.sort_by_key(|(set, _)| **set);
+    for (set, entries) in sets {
+        println!("set {}", set);
+        for entry in entries {
+            println!(
+                "    binding {}: {:?}, visibility {:?}, count {:?}",
+                entry.binding, entry.ty, entry.visibility, entry.count
+            );
+        }
+    }
+    Ok(())
+}
 
-    // let program = session
-    //     .create_composite_component_type(&[
-    //         module.downcast().clone(),
-    //         frag_entry_point.downcast().clone(),
-    //     ])
-    //     .unwrap();
+fn validate(shader: &ShaderArgs) -> Result<(), String> {
+    let program = shader.compile()?;
+    // A WebGPU-compliant device with the features the app requests
+    let capabilities = validation::capabilities(
+        wgpu_tidy::REQUIRED_FEATURES,
+        wgpu::DownlevelFlags::compliant(),
+    );
+    validation::validate(&program, capabilities).map_err(|e| e.to_string())?;
+    println!("{} is valid", shader.module.display());
+    Ok(())
+}
 
-    // let linked_program = program.link().unwrap();
-    // let shader_bytecode = linked_program.target_code(0).unwrap();
+fn codegen(dir: &Path) -> Result<(), String> {
+    let module = wgpu_tidy::shared_module();
+    module
+        .write_to(dir)
+        .map_err(|e| format!("failed to write {}.slang: {}", module.name(), e))?;
+    println!(
+        "Wrote {}",
+        dir.join(format!("{}.slang", module.name())).display()
+    );
+    Ok(())
+}
 
-	
-    // let reflection = linked_program.layout(0).unwrap();
-    // let global_type_layout = reflection.global_params_type_layout();
-    // let global_var_layout = reflection.global_params_var_layout();
-	
-    // println!("Hello, world!");
-    // walk_him_down(global_var_layout, Cursor::fresh(global_type_layout));
-    // let mut entries = HashMap::new();
-    // layout_entries_wowee(global_type_layout, &mut entries, 0, 0);
-    // println!("{:?}", entries);
+fn run() -> Result<(), String> {
+    let event_loop = EventLoop::new().map_err(|e| e.to_string())?;
+    event_loop.set_control_flow(event_loop::ControlFlow::Poll);
+    let mut app_state = AppState::Uninitialized();
+    event_loop
+        .run_app(&mut app_state)
+        .map_err(|e| e.to_string())
+}
 
-    // let mut file = std::fs::File::create("filename.wgsl").unwrap();
-    // file.write_all(shader_bytecode.as_slice()).unwrap();
+fn main() -> ExitCode {
+    let cli = Cli::parse();
+    let result = match cli.command {
+        Command::Compile { shader, out } => compile(&shader, out),
+        Command::Reflect { shader, out } => reflect(&shader, out),
+        Command::Layouts { shader } => layouts(&shader),
+        Command::Validate { shader } => validate(&shader),
+        Command::Codegen { dir } => codegen(&dir),
+        Command::Run => run(),
+    };
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("{}", e);
+            ExitCode::FAILURE
+        }
+    }
 }
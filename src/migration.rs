@@ -0,0 +1,359 @@
+// Carries parameter values over when the shader layout changes on reload.
+// Every uniform leaf (scalar, vector or matrix) and every storage buffer gets a path such as
+// "camera.centre" or "surface.point_data". Values whose path and type match in both layouts are
+// copied on the GPU from the old buffers to the new ones, everything else starts zeroed.
+
+use std::collections::{BTreeMap, HashMap};
+
+use slang::{ParameterCategory, TypeKind, reflection::TypeLayout};
+use wgpu::{Buffer, BufferDescriptor, CommandEncoderDescriptor};
+
+use crate::reflection::Cursor;
+
+#[derive(Debug, Default)]
+pub struct MigrationReport {
+    // Copied over from the old layout
+    pub preserved: Vec<String>,
+    // In the old layout only, or with a different type in the new one
+    pub dropped: Vec<String>,
+    // In the new layout only, or with a different type in the old one
+    pub zeroed: Vec<String>,
+}
+
+impl MigrationReport {
+    pub fn is_lossless(&self) -> bool {
+        self.dropped.is_empty() && self.zeroed.is_empty()
+    }
+}
+
+enum Leaf {
+    Uniform {
+        set: usize,
+        slot: usize,
+        offset: usize,
+        size: usize,
+        signature: String,
+    },
+    // Copied whole, since the Rust side decides how many elements there are
+    Storage {
+        set: usize,
+        slot: usize,
+        signature: String,
+    },
+}
+
+impl Leaf {
+    fn signature(&self) -> &str {
+        match self {
+            Leaf::Uniform { signature, .. } | Leaf::Storage { signature, .. } => signature,
+        }
+    }
+}
+
+// Describes the type and layout, so two leaves with equal signatures hold the same bytes
+fn layout_signature(tl: &TypeLayout) -> String {
+    let mut signature = format!(
+        "{:?}:{}:{}",
+        tl.kind(),
+        tl.name().unwrap_or(""),
+        tl.size(ParameterCategory::Uniform)
+    );
+    if matches!(
+        tl.kind(),
+        TypeKind::Scalar | TypeKind::Vector | TypeKind::Matrix
+    ) && let Some(ty) = tl.ty()
+    {
+        signature.push_str(&format!(":{:?}", ty.scalar_type()));
+    }
+    for i in 0..tl.field_count() {
+        let vl = tl.field_by_index(i).unwrap();
+        signature.push_str(&format!(
+            "{{{}@{}={}}}",
+            vl.variable().map_or("", |v| v.name()),
+            vl.offset(ParameterCategory::Uniform),
+            layout_signature(vl.type_layout())
+        ));
+    }
+    if matches!(tl.kind(), TypeKind::Array | TypeKind::Resource) {
+        signature.push_str(&format!("[{}]", layout_signature(tl.element_type_layout())));
+    }
+    signature
+}
+
+fn collect_leaves(c: Cursor, path: &str, out: &mut BTreeMap<String, Leaf>) {
+    let tl = c.type_layout();
+    let offset = c.offset();
+    match tl.kind() {
+        TypeKind::Scalar | TypeKind::Vector | TypeKind::Matrix => {
+            out.insert(
+                path.to_string(),
+                Leaf::Uniform {
+                    set: offset.set(),
+                    slot: offset.slot(),
+                    offset: offset.uniform(),
+                    size: tl.size(ParameterCategory::Uniform),
+                    signature: layout_signature(tl),
+                },
+            );
+        }
+        TypeKind::Array => {
+            let count = tl.ty().map_or(0, |ty| ty.element_count());
+            for i in 0..count {
+                if let Some(element) = c.navigate_index(i as u32) {
+                    collect_leaves(element, &format!("{}[{}]", path, i), out);
+                }
+            }
+        }
+        // Textures are moved by hot_reload, only buffers hold values
+        TypeKind::Resource
+            if tl
+                .ty()
+                .is_some_and(|ty| ty.name().is_some_and(|n| n.contains("Buffer"))) =>
+        {
+            out.insert(
+                path.to_string(),
+                Leaf::Storage {
+                    set: offset.set(),
+                    slot: offset.slot(),
+                    signature: layout_signature(tl),
+                },
+            );
+        }
+        _ => {
+            for i in 0..tl.field_count() {
+                let name = tl
+                    .field_by_index(i)
+                    .and_then(|vl| vl.variable())
+                    .map_or("", |v| v.name());
+                let field_path = if path.is_empty() {
+                    name.to_string()
+                } else {
+                    format!("{}.{}", path, name)
+                };
+                collect_leaves(c.navigate_field(i).unwrap(), &field_path, out);
+            }
+            if let Some(child) = c.navigate_child() {
+                collect_leaves(child, path, out);
+            }
+        }
+    }
+}
+
+// Every new leaf with the old leaf its value can be copied from, which needs the same path,
+// the same signature and the same kind of leaf
+fn pair_leaves<'a>(
+    old_leaves: &'a BTreeMap<String, Leaf>,
+    new_leaves: &'a BTreeMap<String, Leaf>,
+) -> Vec<(&'a String, Option<&'a Leaf>, &'a Leaf)> {
+    new_leaves
+        .iter()
+        .map(|(path, new_leaf)| {
+            let old_leaf = old_leaves.get(path).filter(|old| {
+                old.signature() == new_leaf.signature()
+                    && matches!(
+                        (old, new_leaf),
+                        (Leaf::Uniform { .. }, Leaf::Uniform { .. })
+                            | (Leaf::Storage { .. }, Leaf::Storage { .. })
+                    )
+            });
+            (path, old_leaf, new_leaf)
+        })
+        .collect()
+}
+
+fn buffer(
+    buffers: &HashMap<usize, HashMap<usize, Buffer>>,
+    set: usize,
+    slot: usize,
+) -> Option<&Buffer> {
+    buffers.get(&set).and_then(|s| s.get(&slot))
+}
+
+// `new_buffers` come straight from `buffers_from_layout`. Storage buffers are replaced by ones
+// as large as the old ones, so the Rust side doesn't have to write them again.
+pub fn migrate_values(
+    old_tl: &TypeLayout,
+    new_tl: &TypeLayout,
+    old_buffers: &HashMap<usize, HashMap<usize, Buffer>>,
+    new_buffers: &mut HashMap<usize, HashMap<usize, Buffer>>,
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+) -> MigrationReport {
+    let mut old_leaves = BTreeMap::new();
+    collect_leaves(Cursor::fresh(old_tl), "", &mut old_leaves);
+    let mut new_leaves = BTreeMap::new();
+    collect_leaves(Cursor::fresh(new_tl), "", &mut new_leaves);
+
+    let mut report = MigrationReport::default();
+    let mut encoder = device.create_command_encoder(&CommandEncoderDescriptor {
+        label: Some("value_migration"),
+    });
+    for (path, old_leaf, new_leaf) in pair_leaves(&old_leaves, &new_leaves) {
+        let copied = match (old_leaf, new_leaf) {
+            (
+                Some(Leaf::Uniform {
+                    set: old_set,
+                    slot: old_slot,
+                    offset: old_offset,
+                    ..
+                }),
+                Leaf::Uniform {
+                    set,
+                    slot,
+                    offset,
+                    size,
+                    ..
+                },
+            ) => match (
+                buffer(old_buffers, *old_set, *old_slot),
+                buffer(new_buffers, *set, *slot),
+            ) {
+                (Some(old_buffer), Some(new_buffer)) => {
+                    encoder.copy_buffer_to_buffer(
+                        old_buffer,
+                        *old_offset as u64,
+                        new_buffer,
+                        *offset as u64,
+                        *size as u64,
+                    );
+                    true
+                }
+                _ => false,
+            },
+            (
+                Some(Leaf::Storage {
+                    set: old_set,
+                    slot: old_slot,
+                    ..
+                }),
+                Leaf::Storage { set, slot, .. },
+            ) => {
+                let old_buffer = buffer(old_buffers, *old_set, *old_slot);
+                let new_buffer = new_buffers.get_mut(set).and_then(|s| s.get_mut(slot));
+                match (old_buffer, new_buffer) {
+                    (Some(old_buffer), Some(new_buffer)) => {
+                        if new_buffer.size() != old_buffer.size() {
+                            *new_buffer = device.create_buffer(&BufferDescriptor {
+                                label: None,
+                                size: old_buffer.size(),
+                                usage: new_buffer.usage(),
+                                mapped_at_creation: false,
+                            });
+                        }
+                        encoder.copy_buffer_to_buffer(
+                            old_buffer,
+                            0,
+                            new_buffer,
+                            0,
+                            old_buffer.size(),
+                        );
+                        true
+                    }
+                    _ => false,
+                }
+            }
+            _ => false,
+        };
+        if copied {
+            report.preserved.push(path.clone());
+        } else {
+            report.zeroed.push(path.clone());
+        }
+    }
+    for path in old_leaves.keys() {
+        if !report.preserved.contains(path) {
+            report.dropped.push(path.clone());
+        }
+    }
+    queue.submit(std::iter::once(encoder.finish()));
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn uniform(offset: usize, signature: &str) -> Leaf {
+        Leaf::Uniform {
+            set: 0,
+            slot: 0,
+            offset,
+            size: 4,
+            signature: signature.to_string(),
+        }
+    }
+
+    fn storage(signature: &str) -> Leaf {
+        Leaf::Storage {
+            set: 0,
+            slot: 1,
+            signature: signature.to_string(),
+        }
+    }
+
+    fn leaves(entries: Vec<(&str, Leaf)>) -> BTreeMap<String, Leaf> {
+        entries
+            .into_iter()
+            .map(|(path, leaf)| (path.to_string(), leaf))
+            .collect()
+    }
+
+    // Paths of the new leaves that found an old one
+    fn paired(old: &BTreeMap<String, Leaf>, new: &BTreeMap<String, Leaf>) -> Vec<String> {
+        pair_leaves(old, new)
+            .into_iter()
+            .filter(|(_, old_leaf, _)| old_leaf.is_some())
+            .map(|(path, _, _)| path.clone())
+            .collect()
+    }
+
+    #[test]
+    fn moved_fields_keep_their_values() {
+        let old = leaves(vec![
+            ("camera.centre", uniform(0, "Vector:float3:12")),
+            ("camera.yfov", uniform(12, "Scalar:float:4")),
+        ]);
+        let new = leaves(vec![
+            ("camera.yfov", uniform(0, "Scalar:float:4")),
+            ("camera.centre", uniform(16, "Vector:float3:12")),
+        ]);
+        let pairs = pair_leaves(&old, &new);
+        assert_eq!(pairs.len(), 2);
+        for (path, old_leaf, _) in pairs {
+            assert!(old_leaf.is_some(), "{}", path);
+        }
+        let (_, Some(Leaf::Uniform { offset, .. }), Leaf::Uniform { offset: to, .. }) =
+            pair_leaves(&old, &new)[0]
+        else {
+            panic!("camera.centre should pair two uniforms");
+        };
+        assert_eq!((*offset, *to), (0, 16));
+    }
+
+    #[test]
+    fn changed_types_are_not_copied() {
+        let old = leaves(vec![("surface.support", uniform(0, "Scalar:float:4"))]);
+        let new = leaves(vec![("surface.support", uniform(0, "Scalar:int:4"))]);
+        assert!(paired(&old, &new).is_empty());
+    }
+
+    #[test]
+    fn new_and_removed_paths_are_unpaired() {
+        let old = leaves(vec![("a", uniform(0, "s")), ("b", uniform(4, "s"))]);
+        let new = leaves(vec![("b", uniform(0, "s")), ("c", uniform(4, "s"))]);
+        assert_eq!(paired(&old, &new), ["b"]);
+    }
+
+    #[test]
+    fn storage_and_uniform_leaves_never_pair() {
+        let old = leaves(vec![
+            ("surface.point_data", storage("buffer")),
+            ("x", uniform(0, "same")),
+        ]);
+        let new = leaves(vec![
+            ("surface.point_data", storage("buffer")),
+            ("x", storage("same")),
+        ]);
+        assert_eq!(paired(&old, &new), ["surface.point_data"]);
+    }
+}
@@ -15,14 +15,15 @@
 use std::{collections::HashMap, num::NonZero, task::Wake};
 
 use bytemuck::Contiguous;
+pub use proc_macros::Writable;
 use slang::{
     ParameterCategory, TypeKind,
     reflection::{TypeLayout, VariableLayout},
 };
 use wgpu::{
     BindGroupEntry, BindGroupLayout, BindGroupLayoutDescriptor, BindGroupLayoutEntry, Buffer,
-    BufferBinding, Sampler, ShaderStages, Texture, TextureView, TextureViewDimension,
-    util::DeviceExt,
+    BufferBinding, Sampler, ShaderStages, StorageTextureAccess, Texture, TextureFormat,
+    TextureView, TextureViewDimension, util::DeviceExt,
 };
 
 #[derive(Debug, Clone, Copy, Default)]
@@ -93,13 +94,9 @@ pub fn buffers_from_layout(
                         wgpu::BufferBindingType::Uniform => {
                             wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_SRC
                         }
-                        wgpu::BufferBindingType::Storage { read_only } => {
-                            wgpu::BufferUsages::STORAGE
-                                | if read_only {
-                                    wgpu::BufferUsages::empty()
-                                } else {
-                                    wgpu::BufferUsages::COPY_SRC
-                                }
+                        // COPY_SRC so values can be carried over on shader reload
+                        wgpu::BufferBindingType::Storage { .. } => {
+                            wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_SRC
                         }
                     };
                 let size = min_binding_size.map_or(0u64, |x| x.into());
@@ -151,11 +148,8 @@ pub fn bind_group_entries_from_layout<'a>(
                                     size: None,
                                 })
                             }
-                            wgpu::BindingType::Texture {
-                                sample_type,
-                                view_dimension,
-                                multisampled,
-                            } => {
+                            wgpu::BindingType::Texture { .. }
+                            | wgpu::BindingType::StorageTexture { .. } => {
                                 let texture_view = binding_resources
                                     .texture_views
                                     .get(k)
@@ -186,6 +180,106 @@ pub fn bind_group_entries_from_layout<'a>(
         .collect()
 }
 
+pub fn view_dimension_of(shape: slang::ResourceShape) -> Option<TextureViewDimension> {
+    match shape {
+        slang::ResourceShape::SlangTexture1d => Some(TextureViewDimension::D1),
+        slang::ResourceShape::SlangTexture2d => Some(TextureViewDimension::D2),
+        slang::ResourceShape::SlangTexture3d => Some(TextureViewDimension::D3),
+        slang::ResourceShape::SlangTextureCube => Some(TextureViewDimension::Cube),
+        slang::ResourceShape::SlangTextureCubeArray => Some(TextureViewDimension::CubeArray),
+        slang::ResourceShape::SlangTexture2dArray => Some(TextureViewDimension::D2Array),
+        _ => None,
+    }
+}
+
+// The texel format a storage texture is declared with, e.g. `[format("rgba16f")]`.
+// None for undeclared formats, which Slang leaves for the backend to guess, and ones wgpu lacks.
+pub fn storage_texture_format(format: slang::ImageFormat) -> Option<TextureFormat> {
+    use slang::ImageFormat as F;
+    Some(match format {
+        F::Rgba32f => TextureFormat::Rgba32Float,
+        F::Rgba16f => TextureFormat::Rgba16Float,
+        F::Rg32f => TextureFormat::Rg32Float,
+        F::Rg16f => TextureFormat::Rg16Float,
+        F::R11fG11fB10f => TextureFormat::Rg11b10Ufloat,
+        F::R32f => TextureFormat::R32Float,
+        F::R16f => TextureFormat::R16Float,
+        F::Rgba16 => TextureFormat::Rgba16Unorm,
+        F::Rgb10A2 => TextureFormat::Rgb10a2Unorm,
+        F::Rgba8 => TextureFormat::Rgba8Unorm,
+        F::Rg16 => TextureFormat::Rg16Unorm,
+        F::Rg8 => TextureFormat::Rg8Unorm,
+        F::R16 => TextureFormat::R16Unorm,
+        F::R8 => TextureFormat::R8Unorm,
+        F::Rgba16Snorm => TextureFormat::Rgba16Snorm,
+        F::Rgba8Snorm => TextureFormat::Rgba8Snorm,
+        F::Rg16Snorm => TextureFormat::Rg16Snorm,
+        F::Rg8Snorm => TextureFormat::Rg8Snorm,
+        F::R16Snorm => TextureFormat::R16Snorm,
+        F::R8Snorm => TextureFormat::R8Snorm,
+        F::Rgba32i => TextureFormat::Rgba32Sint,
+        F::Rgba16i => TextureFormat::Rgba16Sint,
+        F::Rgba8i => TextureFormat::Rgba8Sint,
+        F::Rg32i => TextureFormat::Rg32Sint,
+        F::Rg16i => TextureFormat::Rg16Sint,
+        F::Rg8i => TextureFormat::Rg8Sint,
+        F::R32i => TextureFormat::R32Sint,
+        F::R16i => TextureFormat::R16Sint,
+        F::R8i => TextureFormat::R8Sint,
+        F::Rgba32ui => TextureFormat::Rgba32Uint,
+        F::Rgba16ui => TextureFormat::Rgba16Uint,
+        F::Rgb10A2ui => TextureFormat::Rgb10a2Uint,
+        F::Rgba8ui => TextureFormat::Rgba8Uint,
+        F::Rg32ui => TextureFormat::Rg32Uint,
+        F::Rg16ui => TextureFormat::Rg16Uint,
+        F::Rg8ui => TextureFormat::Rg8Uint,
+        F::R32ui => TextureFormat::R32Uint,
+        F::R16ui => TextureFormat::R16Uint,
+        F::R8ui => TextureFormat::R8Uint,
+        F::R64ui => TextureFormat::R64Uint,
+        F::Bgra8 => TextureFormat::Bgra8Unorm,
+        _ => return None,
+    })
+}
+
+// Shadow samplers (Sampler2DShadow, ...) compare against depth. Integer textures
+// can't be filtered, so they get a non-filtering sampler.
+fn combined_sampler_types(
+    ty: &slang::reflection::Type,
+) -> (wgpu::TextureSampleType, wgpu::SamplerBindingType) {
+    if ty.name().is_some_and(|name| name.contains("Shadow")) {
+        return (
+            wgpu::TextureSampleType::Depth,
+            wgpu::SamplerBindingType::Comparison,
+        );
+    }
+    let result_ty = ty.resource_result_type();
+    let scalar_type = match result_ty.kind() {
+        TypeKind::Vector => result_ty.element_type().scalar_type(),
+        _ => result_ty.scalar_type(),
+    };
+    match scalar_type {
+        slang::ScalarType::Int8
+        | slang::ScalarType::Int16
+        | slang::ScalarType::Int32
+        | slang::ScalarType::Int64 => (
+            wgpu::TextureSampleType::Sint,
+            wgpu::SamplerBindingType::NonFiltering,
+        ),
+        slang::ScalarType::Uint8
+        | slang::ScalarType::Uint16
+        | slang::ScalarType::Uint32
+        | slang::ScalarType::Uint64 => (
+            wgpu::TextureSampleType::Uint,
+            wgpu::SamplerBindingType::NonFiltering,
+        ),
+        _ => (
+            wgpu::TextureSampleType::Float { filterable: true },
+            wgpu::SamplerBindingType::Filtering,
+        ),
+    }
+}
+
 pub fn base_layout_entries(tl: &TypeLayout) -> HashMap<usize, Vec<BindGroupLayoutEntry>> {
     let mut entries = HashMap::new();
     layout_entries_wowee(tl, &mut entries, 0, 0);
@@ -211,10 +305,14 @@ pub fn layout_entries_wowee(
                 }
                 let entry_vec = entries.get_mut(&current_set_index).unwrap();
                 let binding: u32 = (entry_vec.len()).try_into().unwrap();
+                let sampler_binding_type = match leaf_tl.ty().and_then(|ty| ty.name()) {
+                    Some("SamplerComparisonState") => wgpu::SamplerBindingType::Comparison,
+                    _ => wgpu::SamplerBindingType::Filtering,
+                };
                 entry_vec.push(BindGroupLayoutEntry {
                     binding,
                     visibility: ShaderStages::all(),
-                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    ty: wgpu::BindingType::Sampler(sampler_binding_type),
                     count: None,
                 });
             }
@@ -226,17 +324,7 @@ pub fn layout_entries_wowee(
                 let entry_vec = entries.get_mut(&current_set_index).unwrap();
                 let binding = (entry_vec.len()).try_into().unwrap();
                 let leaf_ty = leaf_tl.ty().unwrap();
-                let view_dimension = match leaf_ty.resource_shape() {
-                    slang::ResourceShape::SlangTexture1d => TextureViewDimension::D1,
-                    slang::ResourceShape::SlangTexture2d => TextureViewDimension::D2,
-                    slang::ResourceShape::SlangTexture3d => TextureViewDimension::D3,
-                    slang::ResourceShape::SlangTextureCube => TextureViewDimension::Cube,
-                    slang::ResourceShape::SlangTextureCubeArray => TextureViewDimension::CubeArray,
-                    slang::ResourceShape::SlangTexture2dArray => TextureViewDimension::D2Array,
-                    _ => {
-                        unreachable!()
-                    }
-                };
+                let view_dimension = view_dimension_of(leaf_ty.resource_shape()).unwrap();
                 entry_vec.push(BindGroupLayoutEntry {
                     binding,
                     visibility: ShaderStages::all(),
@@ -248,6 +336,85 @@ pub fn layout_entries_wowee(
                     count: None,
                 });
             }
+            slang::BindingType::MutableTexture => {
+                if !entries.contains_key(&current_set_index) {
+                    entries.insert(current_set_index, vec![]);
+                    next_set_index += 1;
+                }
+                let entry_vec = entries.get_mut(&current_set_index).unwrap();
+                let binding = (entry_vec.len()).try_into().unwrap();
+                let leaf_ty = leaf_tl.ty().unwrap();
+                let view_dimension = view_dimension_of(leaf_ty.resource_shape()).unwrap();
+                let name = tl
+                    .binding_range_leaf_variable(i)
+                    .map_or("<anon_var>", |v| v.name());
+                let access = match leaf_ty.resource_access() {
+                    slang::ResourceAccess::Read => StorageTextureAccess::ReadOnly,
+                    slang::ResourceAccess::Write => StorageTextureAccess::WriteOnly,
+                    slang::ResourceAccess::ReadWrite => StorageTextureAccess::ReadWrite,
+                    access => panic!(
+                        "storage texture {} has {:?} access, which wgpu can't bind",
+                        name, access
+                    ),
+                };
+                let image_format = tl.binding_range_image_format(i);
+                let format = storage_texture_format(image_format).unwrap_or_else(|| {
+                    panic!(
+                        "storage texture {} has format {:?}, declare it with a format wgpu \
+                         supports, e.g. [format(\"rgba16f\")]",
+                        name, image_format
+                    )
+                });
+                entry_vec.push(BindGroupLayoutEntry {
+                    binding,
+                    visibility: ShaderStages::all(),
+                    ty: wgpu::BindingType::StorageTexture {
+                        access,
+                        format,
+                        view_dimension,
+                    },
+                    count: None,
+                });
+            }
+            // WGSL has no combined samplers, so Slang splits these into a texture
+            // and a sampler on adjacent bindings, texture first. Other targets keep
+            // them in one binding, which wgpu has no binding type for.
+            slang::BindingType::CombinedTextureSampler => {
+                let slots = leaf_tl.size(ParameterCategory::DescriptorTableSlot);
+                let leaf_ty = leaf_tl.ty().unwrap();
+                assert_eq!(
+                    slots,
+                    2,
+                    "combined texture-sampler {} takes {} binding(s), wgpu needs a separate \
+                     texture and sampler",
+                    leaf_ty.name().unwrap_or("<anon_type>"),
+                    slots
+                );
+                if !entries.contains_key(&current_set_index) {
+                    entries.insert(current_set_index, vec![]);
+                    next_set_index += 1;
+                }
+                let entry_vec = entries.get_mut(&current_set_index).unwrap();
+                let binding: u32 = (entry_vec.len()).try_into().unwrap();
+                let view_dimension = view_dimension_of(leaf_ty.resource_shape()).unwrap();
+                let (sample_type, sampler_binding_type) = combined_sampler_types(leaf_ty);
+                entry_vec.push(BindGroupLayoutEntry {
+                    binding,
+                    visibility: ShaderStages::all(),
+                    ty: wgpu::BindingType::Texture {
+                        sample_type,
+                        view_dimension,
+                        multisampled: false,
+                    },
+                    count: None,
+                });
+                entry_vec.push(BindGroupLayoutEntry {
+                    binding: binding + 1,
+                    visibility: ShaderStages::all(),
+                    ty: wgpu::BindingType::Sampler(sampler_binding_type),
+                    count: None,
+                });
+            }
             slang::BindingType::ConstantBuffer => {
                 if leaf_tl
                     .container_var_layout()
@@ -449,6 +616,12 @@ impl<'a> Cursor<'a> {
         let field_var = self.type_layout.field_by_index(field_index)?;
         self.navigate_into_var(field_var)
     }
+    pub fn navigate_field_by_name(&self, name: &str) -> Option<Cursor<'a>> {
+        let field_var = (0..self.type_layout.field_count())
+            .filter_map(|i| self.type_layout.field_by_index(i))
+            .find(|vl| vl.variable().is_some_and(|v| v.name() == name))?;
+        self.navigate_into_var(field_var)
+    }
     pub fn navigate_child(&self) -> Option<Cursor<'a>> {
         match self.type_layout.kind() {
             TypeKind::ConstantBuffer
@@ -571,6 +744,18 @@ impl<'a> Cursor<'a> {
         }
     }
 
+    // The sampler half of a combined texture-sampler, one binding after the texture.
+    pub fn combined_sampler(&self) -> Cursor<'a> {
+        Cursor {
+            type_layout: self.type_layout,
+            offset: Offset {
+                slot: self.offset.slot + 1,
+                slot_accumulator: self.offset.slot_accumulator + 1,
+                ..self.offset
+            },
+        }
+    }
+
     pub fn type_layout(&self) -> &TypeLayout {
         self.type_layout
     }
@@ -597,3 +782,64 @@ pub fn walk_him_down(vl: &VariableLayout, c: Cursor) {
         walk_him_down(vl.type_layout().element_var_layout(), ec);
     }
 }
+
+// The parameter tree as JSON, with the set, slot and uniform offset the cursor assigns to each node.
+// Arrays show their first element only.
+pub fn parameter_tree(name: &str, c: Cursor) -> serde_json::Value {
+    let tl = c.type_layout();
+    let offset = c.offset();
+    let mut node = serde_json::json!({
+        "name": name,
+        "type": tl.name(),
+        "kind": format!("{:?}", tl.kind()),
+        "set": offset.set(),
+        "slot": offset.slot(),
+        "uniform_offset": offset.uniform(),
+        "uniform_size": tl.size(ParameterCategory::Uniform),
+    });
+    let fields: Vec<_> = (0..tl.field_count())
+        .map(|i| {
+            let field_name = tl
+                .field_by_index(i)
+                .and_then(|vl| vl.variable())
+                .map_or("", |v| v.name());
+            parameter_tree(field_name, c.navigate_field(i).unwrap())
+        })
+        .collect();
+    if !fields.is_empty() {
+        node["fields"] = fields.into();
+    }
+    if let Some(child) = c.navigate_child() {
+        node["element"] = parameter_tree("", child);
+    } else if tl.kind() == TypeKind::Array {
+        node["count"] = tl.ty().map_or(0, |ty| ty.element_count()).into();
+        if let Some(element) = c.navigate_index(0) {
+            node["element"] = parameter_tree("", element);
+        }
+    }
+    node
+}
+
+// Set and slot of every resource leaf, keyed by field path ("background0", "surface.point_data").
+// The keys are what the Writables use to index BindingResources.
+pub fn resource_slots(c: Cursor, path: &str, out: &mut HashMap<String, (usize, usize)>) {
+    let tl = c.type_layout();
+    if matches!(tl.kind(), TypeKind::Resource | TypeKind::SamplerState) {
+        out.insert(path.to_string(), (c.offset().set(), c.offset().slot()));
+    }
+    for i in 0..tl.field_count() {
+        let name = tl
+            .field_by_index(i)
+            .and_then(|vl| vl.variable())
+            .map_or("", |v| v.name());
+        let field_path = if path.is_empty() {
+            name.to_string()
+        } else {
+            format!("{}.{}", path, name)
+        };
+        resource_slots(c.navigate_field(i).unwrap(), &field_path, out);
+    }
+    if let Some(child) = c.navigate_child() {
+        resource_slots(child, path, out);
+    }
+}
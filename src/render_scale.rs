@@ -0,0 +1,274 @@
+// Dynamic resolution. `fragment`, or the compute path's blit, draws into an offscreen target at a
+// fraction of the window size, and upscale.slang stretches that over the surface. `RenderScale`
+// picks the fraction from measured frame times, stepping down when frames run over the target
+// and back up when the next step's extra pixels would still fit.
+
+use std::{collections::HashMap, error::Error, time::Duration};
+
+use naga::valid::Capabilities;
+use wgpu::{
+    BindGroup, BindGroupDescriptor, BindGroupLayout, CommandEncoderDescriptor, RenderPipeline,
+    TextureFormat, TextureView,
+};
+
+use crate::{
+    compiler::{ShaderCompiler, ShaderProgram},
+    create_render_pipeline,
+    reflection::{BindingResources, Cursor, Writable, bind_group_entries_from_layout},
+    sampler::{Sampler, SamplerConfig},
+    texture::RenderTarget2D,
+    trace::empty_resources,
+    tuning::Tuning,
+    validation,
+};
+
+pub const UPSCALE_MODULE: &str = "upscale.slang";
+
+const MIN_SCALE: f32 = 0.25;
+const MAX_SCALE: f32 = 1.0;
+// Coarse steps keep the offscreen target from being recreated every few frames
+const SCALE_STEP: f32 = 0.125;
+// Frames to average after a change before the next one
+const SETTLE_FRAMES: u32 = 10;
+// Frame times this far over the target step the scale down
+const TOLERANCE: f32 = 0.15;
+
+pub struct RenderScale {
+    scale: f32,
+    target_frame_time: Duration,
+    // Exponential moving average since the last change
+    frame_time: Option<Duration>,
+    frames_at_scale: u32,
+}
+
+impl RenderScale {
+    pub fn new(target_frame_time: Duration) -> Self {
+        RenderScale {
+            scale: MAX_SCALE,
+            target_frame_time,
+            frame_time: None,
+            frames_at_scale: 0,
+        }
+    }
+
+    pub fn scale(&self) -> f32 {
+        self.scale
+    }
+
+    pub fn target_frame_time(&self) -> Duration {
+        self.target_frame_time
+    }
+
+    // Returns whether the scale changed
+    pub fn record_frame(&mut self, frame_time: Duration) -> bool {
+        let average = match self.frame_time {
+            Some(average) => average.mul_f32(0.9) + frame_time.mul_f32(0.1),
+            None => frame_time,
+        };
+        self.frame_time = Some(average);
+        self.frames_at_scale += 1;
+        if self.frames_at_scale < SETTLE_FRAMES {
+            return false;
+        }
+
+        // Cost goes with the pixel count, the square of the scale
+        let load = average.as_secs_f32() / self.target_frame_time.as_secs_f32();
+        let up = (self.scale + SCALE_STEP).min(MAX_SCALE);
+        let new_scale = if load > 1.0 + TOLERANCE {
+            (self.scale - SCALE_STEP).max(MIN_SCALE)
+        } else if load * (up / self.scale).powi(2) < 1.0 {
+            up
+        } else {
+            self.scale
+        };
+        if new_scale == self.scale {
+            return false;
+        }
+        self.scale = new_scale;
+        self.frame_time = None;
+        self.frames_at_scale = 0;
+        true
+    }
+
+    // Never zero, wgpu refuses empty textures
+    pub fn scaled_size(&self, width: u32, height: u32) -> (u32, u32) {
+        let scale = |n: u32| ((n as f32 * self.scale).round() as u32).max(1);
+        (scale(width), scale(height))
+    }
+}
+
+// The offscreen target the view is drawn into and the pipeline that upscales it
+pub struct ScaledView {
+    upscale: ShaderProgram,
+    upscale_pipeline: RenderPipeline,
+    upscale_layouts: HashMap<usize, BindGroupLayout>,
+    upscale_resources: BindingResources,
+    target: RenderTarget2D,
+    sampler: Sampler,
+}
+
+impl ScaledView {
+    // The target has the surface's format, so the main render pipeline can draw into it as is
+    pub fn new(
+        device: &wgpu::Device,
+        compiler: &ShaderCompiler,
+        capabilities: Capabilities,
+        surface_format: TextureFormat,
+        tuning: &Tuning,
+        width: u32,
+        height: u32,
+    ) -> Result<Self, Box<dyn Error>> {
+        let upscale = compiler.compile(UPSCALE_MODULE)?;
+        validation::validate(&upscale, capabilities)?;
+        let (upscale_pipeline, upscale_layouts) =
+            create_render_pipeline(device, &upscale, surface_format, tuning);
+
+        Ok(ScaledView {
+            upscale,
+            upscale_pipeline,
+            upscale_layouts,
+            upscale_resources: empty_resources(),
+            target: RenderTarget2D::new(width, height, surface_format),
+            sampler: Sampler::new(SamplerConfig::trilinear())?,
+        })
+    }
+
+    pub fn size(&self) -> (u32, u32) {
+        self.target.size()
+    }
+
+    pub fn resize(&mut self, width: u32, height: u32) {
+        self.target.resize(width, height);
+    }
+
+    // For the color attachment of the pass drawing the scene
+    pub fn target_view(&self, device: &wgpu::Device) -> TextureView {
+        self.target.view(device)
+    }
+
+    // Draws the target over all of `surface_view`
+    pub fn upscale(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        surface_view: &TextureView,
+    ) {
+        let top_cursor = Cursor::fresh(self.upscale.global_type_layout());
+        self.target.write_at_cursor(
+            top_cursor.navigate_field_by_name("scaled").unwrap(),
+            device,
+            queue,
+            &mut self.upscale_resources,
+        );
+        self.sampler.write_at_cursor(
+            top_cursor.navigate_field_by_name("scaled_sampler").unwrap(),
+            device,
+            queue,
+            &mut self.upscale_resources,
+        );
+        let bind_groups: HashMap<usize, BindGroup> =
+            bind_group_entries_from_layout(self.upscale.layout_entries(), &self.upscale_resources)
+                .iter()
+                .map(|(&set, entries)| {
+                    (
+                        set,
+                        device.create_bind_group(&BindGroupDescriptor {
+                            label: Some(&format!("upscale_bg{}", set)),
+                            layout: &self.upscale_layouts[&set],
+                            entries,
+                        }),
+                    )
+                })
+                .collect();
+
+        let mut encoder = device.create_command_encoder(&CommandEncoderDescriptor {
+            label: Some("upscale_encoder"),
+        });
+        {
+            let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("upscale_pass"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: surface_view,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                        store: wgpu::StoreOp::Store,
+                    },
+                })],
+                depth_stencil_attachment: None,
+                timestamp_writes: None,
+                occlusion_query_set: None,
+            });
+            render_pass.set_pipeline(&self.upscale_pipeline);
+            for (&set, bind_group) in &bind_groups {
+                render_pass.set_bind_group(set as u32, bind_group, &[]);
+            }
+            render_pass.draw(0..3, 0..1);
+        }
+        queue.submit(std::iter::once(encoder.finish()));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TARGET: Duration = Duration::from_millis(16);
+
+    // Enough frames of `frame_time` for the average to settle and the scale to be reconsidered
+    fn settle(scale: &mut RenderScale, frame_time: Duration) -> bool {
+        (0..SETTLE_FRAMES).any(|_| scale.record_frame(frame_time))
+    }
+
+    #[test]
+    fn starts_at_full_scale() {
+        assert_eq!(RenderScale::new(TARGET).scale(), MAX_SCALE);
+    }
+
+    #[test]
+    fn waits_for_the_average_to_settle() {
+        let mut scale = RenderScale::new(TARGET);
+        for _ in 1..SETTLE_FRAMES {
+            assert!(!scale.record_frame(TARGET * 4));
+        }
+        assert!(scale.record_frame(TARGET * 4));
+        assert_eq!(scale.scale(), MAX_SCALE - SCALE_STEP);
+    }
+
+    #[test]
+    fn slow_frames_step_down_to_the_minimum() {
+        let mut scale = RenderScale::new(TARGET);
+        while settle(&mut scale, TARGET * 4) {}
+        assert_eq!(scale.scale(), MIN_SCALE);
+    }
+
+    #[test]
+    fn fast_frames_step_back_up() {
+        let mut scale = RenderScale::new(TARGET);
+        assert!(settle(&mut scale, TARGET * 4));
+        assert!(settle(&mut scale, TARGET * 4));
+        assert!(settle(&mut scale, TARGET / 4));
+        assert_eq!(scale.scale(), MAX_SCALE - SCALE_STEP);
+        assert!(settle(&mut scale, TARGET / 4));
+        assert_eq!(scale.scale(), MAX_SCALE);
+        assert!(!settle(&mut scale, TARGET / 4));
+    }
+
+    // Within tolerance of the target, but the next step up would be too much
+    #[test]
+    fn holds_near_the_target() {
+        let mut scale = RenderScale::new(TARGET);
+        assert!(settle(&mut scale, TARGET * 4));
+        assert!(!settle(&mut scale, TARGET));
+        assert_eq!(scale.scale(), MAX_SCALE - SCALE_STEP);
+    }
+
+    #[test]
+    fn scaled_size_rounds_and_is_never_zero() {
+        let mut scale = RenderScale::new(TARGET);
+        assert_eq!(scale.scaled_size(1280, 720), (1280, 720));
+        settle(&mut scale, TARGET * 4);
+        assert_eq!(scale.scaled_size(1280, 720), (1120, 630));
+        assert_eq!(scale.scaled_size(1, 1), (1, 1));
+    }
+}
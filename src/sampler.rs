@@ -0,0 +1,273 @@
+use std::{cell::Cell, error::Error, fmt};
+
+use wgpu::{AddressMode, CompareFunction, FilterMode, SamplerDescriptor};
+
+use crate::{
+    reflection::{BindingResources, Cursor, Writable},
+    skybox::RgbaSkybox,
+    texture::Texture2D,
+};
+
+// Anisotropy above 1 needs all three filters to be Linear, otherwise wgpu rejects the sampler,
+// so `Sampler` checks that before taking a config.
+// A compare function only works on SamplerComparisonState bindings.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SamplerConfig {
+    pub address_mode_u: AddressMode,
+    pub address_mode_v: AddressMode,
+    pub address_mode_w: AddressMode,
+    pub mag_filter: FilterMode,
+    pub min_filter: FilterMode,
+    pub mipmap_filter: FilterMode,
+    pub lod_min_clamp: f32,
+    pub lod_max_clamp: f32,
+    pub anisotropy_clamp: u16,
+    pub compare: Option<CompareFunction>,
+}
+
+impl SamplerConfig {
+    pub fn nearest() -> Self {
+        SamplerConfig {
+            address_mode_u: AddressMode::ClampToEdge,
+            address_mode_v: AddressMode::ClampToEdge,
+            address_mode_w: AddressMode::ClampToEdge,
+            mag_filter: FilterMode::Nearest,
+            min_filter: FilterMode::Nearest,
+            mipmap_filter: FilterMode::Nearest,
+            lod_min_clamp: 0.0,
+            lod_max_clamp: 32.0,
+            anisotropy_clamp: 1,
+            compare: None,
+        }
+    }
+
+    pub fn trilinear() -> Self {
+        SamplerConfig {
+            mag_filter: FilterMode::Linear,
+            min_filter: FilterMode::Linear,
+            mipmap_filter: FilterMode::Linear,
+            ..Self::nearest()
+        }
+    }
+
+    pub fn anisotropic(anisotropy_clamp: u16) -> Self {
+        SamplerConfig {
+            anisotropy_clamp,
+            ..Self::trilinear()
+        }
+    }
+
+    pub fn with_address_mode(self, address_mode: AddressMode) -> Self {
+        SamplerConfig {
+            address_mode_u: address_mode,
+            address_mode_v: address_mode,
+            address_mode_w: address_mode,
+            ..self
+        }
+    }
+
+    // The rules wgpu checks in `create_sampler`, caught before the sampler is first written
+    pub fn validate(&self) -> Result<(), SamplerError> {
+        if self.anisotropy_clamp == 0 {
+            return Err(SamplerError::ZeroAnisotropy);
+        }
+        let filters = [self.mag_filter, self.min_filter, self.mipmap_filter];
+        if self.anisotropy_clamp > 1 && filters.contains(&FilterMode::Nearest) {
+            return Err(SamplerError::AnisotropyNeedsLinear {
+                anisotropy_clamp: self.anisotropy_clamp,
+            });
+        }
+        Ok(())
+    }
+
+    pub fn descriptor(&self) -> SamplerDescriptor<'static> {
+        SamplerDescriptor {
+            label: None,
+            address_mode_u: self.address_mode_u,
+            address_mode_v: self.address_mode_v,
+            address_mode_w: self.address_mode_w,
+            mag_filter: self.mag_filter,
+            min_filter: self.min_filter,
+            mipmap_filter: self.mipmap_filter,
+            lod_min_clamp: self.lod_min_clamp,
+            lod_max_clamp: self.lod_max_clamp,
+            compare: self.compare,
+            anisotropy_clamp: self.anisotropy_clamp,
+            border_color: None,
+        }
+    }
+}
+
+impl Default for SamplerConfig {
+    fn default() -> Self {
+        Self::trilinear()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SamplerError {
+    ZeroAnisotropy,
+    AnisotropyNeedsLinear { anisotropy_clamp: u16 },
+}
+
+impl fmt::Display for SamplerError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SamplerError::ZeroAnisotropy => write!(f, "anisotropy_clamp has to be at least 1"),
+            SamplerError::AnisotropyNeedsLinear { anisotropy_clamp } => write!(
+                f,
+                "anisotropy_clamp {} needs Linear mag, min and mipmap filters",
+                anisotropy_clamp
+            ),
+        }
+    }
+}
+
+impl Error for SamplerError {}
+
+// The wgpu sampler is only recreated when the config differs from the one
+// last written, or when the slot has no sampler yet.
+pub struct Sampler {
+    config: SamplerConfig,
+    written: Cell<Option<SamplerConfig>>,
+}
+
+impl Sampler {
+    pub fn new(config: SamplerConfig) -> Result<Self, SamplerError> {
+        config.validate()?;
+        Ok(Sampler {
+            config,
+            written: Cell::new(None),
+        })
+    }
+
+    pub fn config(&self) -> &SamplerConfig {
+        &self.config
+    }
+
+    // Keeps the old config if the new one is invalid
+    pub fn set_config(&mut self, config: SamplerConfig) -> Result<(), SamplerError> {
+        config.validate()?;
+        self.config = config;
+        Ok(())
+    }
+
+    // Whether the slot needs a new wgpu sampler
+    fn is_stale(&self, slot_has_sampler: bool) -> bool {
+        !slot_has_sampler || self.written.get() != Some(self.config)
+    }
+}
+
+impl Writable for Sampler {
+    fn write_at_cursor(
+        &self,
+        c: Cursor,
+        device: &wgpu::Device,
+        _queue: &wgpu::Queue,
+        binding_resources: &mut BindingResources,
+    ) {
+        let set = binding_resources
+            .samplers
+            .entry(c.offset().set())
+            .or_default();
+        let slot_index = c.offset().slot();
+        if self.is_stale(set.contains_key(&slot_index)) {
+            set.insert(slot_index, device.create_sampler(&self.config.descriptor()));
+            self.written.set(Some(self.config));
+        }
+    }
+}
+
+// Fills both halves of a Slang combined texture-sampler (Sampler2D, SamplerCube, ...).
+pub struct CombinedTextureSampler<T> {
+    pub texture: T,
+    pub sampler: Sampler,
+}
+
+pub type Sampler2D = CombinedTextureSampler<Texture2D>;
+pub type SamplerCube = CombinedTextureSampler<RgbaSkybox>;
+
+impl<T> CombinedTextureSampler<T> {
+    pub fn new(texture: T, config: SamplerConfig) -> Result<Self, SamplerError> {
+        Ok(CombinedTextureSampler {
+            texture,
+            sampler: Sampler::new(config)?,
+        })
+    }
+}
+
+impl<T: Writable> Writable for CombinedTextureSampler<T> {
+    fn write_at_cursor(
+        &self,
+        c: Cursor,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        binding_resources: &mut BindingResources,
+    ) {
+        self.texture
+            .write_at_cursor(c, device, queue, binding_resources);
+        self.sampler
+            .write_at_cursor(c.combined_sampler(), device, queue, binding_resources);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn presets_are_valid() {
+        for config in [
+            SamplerConfig::nearest(),
+            SamplerConfig::trilinear(),
+            SamplerConfig::anisotropic(16),
+        ] {
+            assert_eq!(config.validate(), Ok(()));
+        }
+    }
+
+    #[test]
+    fn anisotropy_needs_linear_filters() {
+        let config = SamplerConfig {
+            mipmap_filter: FilterMode::Nearest,
+            ..SamplerConfig::anisotropic(8)
+        };
+        assert_eq!(
+            Sampler::new(config).err(),
+            Some(SamplerError::AnisotropyNeedsLinear {
+                anisotropy_clamp: 8
+            })
+        );
+        let config = SamplerConfig {
+            anisotropy_clamp: 0,
+            ..SamplerConfig::trilinear()
+        };
+        assert_eq!(config.validate(), Err(SamplerError::ZeroAnisotropy));
+    }
+
+    #[test]
+    fn invalid_configs_are_not_taken() {
+        let mut sampler = Sampler::new(SamplerConfig::nearest()).unwrap();
+        let invalid = SamplerConfig {
+            anisotropy_clamp: 4,
+            ..SamplerConfig::nearest()
+        };
+        assert!(sampler.set_config(invalid).is_err());
+        assert_eq!(*sampler.config(), SamplerConfig::nearest());
+    }
+
+    #[test]
+    fn recreated_only_when_the_config_changes() {
+        let mut sampler = Sampler::new(SamplerConfig::trilinear()).unwrap();
+        assert!(sampler.is_stale(false));
+        sampler.written.set(Some(*sampler.config()));
+        assert!(!sampler.is_stale(true));
+        // A slot that lost its sampler gets a new one either way
+        assert!(sampler.is_stale(false));
+        sampler.set_config(SamplerConfig::nearest()).unwrap();
+        assert!(sampler.is_stale(true));
+        // Setting the written config again changes nothing
+        sampler.set_config(SamplerConfig::trilinear()).unwrap();
+        assert!(!sampler.is_stale(true));
+    }
+}
@@ -0,0 +1,188 @@
+// Emitted shader code on disk, so unchanged shaders skip Slang's code generation, which is where
+// the unrolled and differentiated code spends most of its compile time.
+// Loading and linking the module still happen on a hit, because bind group layouts, writes and
+// value migration all read the linked program's reflection, so a hit saves code generation only.
+// An entry is `<key>.code` plus `<key>.json` holding the reflection summary it was compiled with.
+// Anything missing, unreadable or not matching the current reflection is a miss.
+
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+// Bump when the entry format or the way code is generated changes
+const CACHE_VERSION: u32 = 1;
+
+pub struct ShaderCache {
+    dir: PathBuf,
+}
+
+// Everything that affects the emitted code
+pub struct CacheInputs<'a> {
+    pub module: &'a str,
+    pub search_paths: &'a [PathBuf],
+    pub macros: &'a [(String, String)],
+    pub target: &'a str,
+    pub profile: &'a str,
+    pub entry_points: &'a [String],
+    pub all_entry_points: bool,
+}
+
+impl ShaderCache {
+    pub fn new(dir: impl AsRef<Path>) -> Self {
+        ShaderCache {
+            dir: dir.as_ref().to_path_buf(),
+        }
+    }
+
+    // Hashes every .slang file Slang could load from the search paths, not only the ones the
+    // module imports, so an edit anywhere in them changes the key.
+    pub fn key(&self, inputs: &CacheInputs) -> io::Result<String> {
+        let mut hasher = blake3::Hasher::new();
+        let mut field = |bytes: &[u8]| {
+            hasher.update(&(bytes.len() as u64).to_le_bytes());
+            hasher.update(bytes);
+        };
+        field(&CACHE_VERSION.to_le_bytes());
+        field(env!("CARGO_PKG_VERSION").as_bytes());
+        field(inputs.module.as_bytes());
+        field(inputs.target.as_bytes());
+        field(inputs.profile.as_bytes());
+        for entry_point in inputs.entry_points {
+            field(entry_point.as_bytes());
+        }
+        field(&[inputs.all_entry_points as u8]);
+        for (name, value) in inputs.macros {
+            field(name.as_bytes());
+            field(value.as_bytes());
+        }
+        for dir in inputs.search_paths {
+            let mut sources: Vec<PathBuf> = fs::read_dir(dir)?
+                .filter_map(|entry| entry.ok().map(|e| e.path()))
+                .filter(|p| p.extension().is_some_and(|ext| ext == "slang"))
+                .collect();
+            sources.sort();
+            for source in sources {
+                field(source.to_string_lossy().as_bytes());
+                field(&fs::read(&source)?);
+            }
+        }
+        Ok(hasher.finalize().to_hex().to_string())
+    }
+
+    pub fn load(&self, key: &str, reflection: &serde_json::Value) -> Option<Vec<u8>> {
+        let summary = fs::read(self.dir.join(format!("{}.json", key))).ok()?;
+        let summary: serde_json::Value = serde_json::from_slice(&summary).ok()?;
+        if summary["version"] != CACHE_VERSION || summary["reflection"] != *reflection {
+            return None;
+        }
+        fs::read(self.dir.join(format!("{}.code", key))).ok()
+    }
+
+    // The summary is removed first and written last, so a half-written entry never loads
+    pub fn store(&self, key: &str, code: &[u8], reflection: &serde_json::Value) -> io::Result<()> {
+        fs::create_dir_all(&self.dir)?;
+        let summary_path = self.dir.join(format!("{}.json", key));
+        if summary_path.exists() {
+            fs::remove_file(&summary_path)?;
+        }
+        fs::write(self.dir.join(format!("{}.code", key)), code)?;
+        let summary = serde_json::json!({
+            "version": CACHE_VERSION,
+            "reflection": reflection,
+        });
+        fs::write(summary_path, serde_json::to_string_pretty(&summary)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A fresh directory holding `shader/` with one source and `cache/` for the entries
+    fn scratch(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("wgpu_tidy_cache_{}", name));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(dir.join("shader")).unwrap();
+        fs::write(dir.join("shader/gorilla.slang"), "float4 shade() {}").unwrap();
+        dir
+    }
+
+    fn key(dir: &Path, macros: &[(String, String)], target: &str) -> String {
+        let search_paths = [dir.join("shader")];
+        ShaderCache::new(dir.join("cache"))
+            .key(&CacheInputs {
+                module: "gorilla.slang",
+                search_paths: &search_paths,
+                macros,
+                target,
+                profile: "glsl_450",
+                entry_points: &[],
+                all_entry_points: true,
+            })
+            .unwrap()
+    }
+
+    #[test]
+    fn any_source_in_the_search_paths_changes_the_key() {
+        let dir = scratch("sources");
+        let before = key(&dir, &[], "wgsl");
+        assert_eq!(key(&dir, &[], "wgsl"), before);
+        // Not imported by gorilla.slang, still part of the key
+        fs::write(dir.join("shader/unrelated.slang"), "").unwrap();
+        let added = key(&dir, &[], "wgsl");
+        assert_ne!(added, before);
+        fs::write(dir.join("shader/unrelated.slang"), "int x;").unwrap();
+        let edited = key(&dir, &[], "wgsl");
+        assert_ne!(edited, added);
+        // Other files are ignored
+        fs::write(dir.join("shader/notes.txt"), "").unwrap();
+        assert_eq!(key(&dir, &[], "wgsl"), edited);
+    }
+
+    #[test]
+    fn macros_and_target_change_the_key() {
+        let dir = scratch("options");
+        let steps = |n: &str| [("KSTEPS".to_string(), n.to_string())];
+        let base = key(&dir, &steps("10"), "wgsl");
+        assert_ne!(key(&dir, &steps("12"), "wgsl"), base);
+        assert_ne!(key(&dir, &[], "wgsl"), base);
+        assert_ne!(key(&dir, &steps("10"), "spirv"), base);
+    }
+
+    #[test]
+    fn store_then_load_round_trips() {
+        let dir = scratch("round_trip");
+        let cache = ShaderCache::new(dir.join("cache"));
+        let reflection = serde_json::json!({ "camera": "Camera" });
+        cache.store("k", b"code", &reflection).unwrap();
+        assert_eq!(cache.load("k", &reflection).as_deref(), Some(&b"code"[..]));
+        assert_eq!(cache.load("other", &reflection), None);
+    }
+
+    #[test]
+    fn changed_reflection_is_a_miss() {
+        let dir = scratch("reflection");
+        let cache = ShaderCache::new(dir.join("cache"));
+        cache
+            .store("k", b"code", &serde_json::json!({ "camera": "Camera" }))
+            .unwrap();
+        assert_eq!(
+            cache.load("k", &serde_json::json!({ "camera": "Camera2" })),
+            None
+        );
+    }
+
+    #[test]
+    fn missing_or_corrupt_summary_is_a_miss() {
+        let dir = scratch("corrupt");
+        let cache = ShaderCache::new(dir.join("cache"));
+        let reflection = serde_json::json!({});
+        cache.store("k", b"code", &reflection).unwrap();
+        let summary = dir.join("cache/k.json");
+        fs::write(&summary, "{ not json").unwrap();
+        assert_eq!(cache.load("k", &reflection), None);
+        fs::remove_file(&summary).unwrap();
+        assert_eq!(cache.load("k", &reflection), None);
+    }
+}
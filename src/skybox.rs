@@ -0,0 +1,723 @@
+use std::{
+    cell::Cell,
+    f32::consts::{PI, TAU},
+    fmt,
+    path::{Path, PathBuf},
+};
+
+use cgmath::{InnerSpace, Vector3};
+use image::{DynamicImage, ImageError, Rgba, Rgba32FImage};
+use wgpu::{TextureDimension, TextureFormat, TextureViewDimension};
+
+use crate::{
+    reflection::{BindingResources, Cursor, Writable},
+    texture::{
+        MipFilter, TextureData, TextureError, check_view_dimension, default_format,
+        image_as_rgba32f, upload_texture,
+    },
+};
+
+#[derive(Debug)]
+pub enum SkyboxError {
+    MissingFace {
+        face: SourceFace,
+        path: PathBuf,
+    },
+    Image {
+        path: PathBuf,
+        source: ImageError,
+    },
+    NonSquareFace {
+        path: PathBuf,
+        width: u32,
+        height: u32,
+    },
+    MismatchedSize {
+        path: PathBuf,
+        expected: u32,
+        width: u32,
+        height: u32,
+    },
+    ZeroFaceSize {
+        path: PathBuf,
+    },
+    BadCrossSize {
+        path: PathBuf,
+        layout: CrossLayout,
+        width: u32,
+        height: u32,
+    },
+    Texture(TextureError),
+}
+
+impl From<TextureError> for SkyboxError {
+    fn from(e: TextureError) -> Self {
+        SkyboxError::Texture(e)
+    }
+}
+
+impl fmt::Display for SkyboxError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SkyboxError::MissingFace { face, path } => {
+                write!(f, "{}: missing {:?} face", path.display(), face)
+            }
+            SkyboxError::Image { path, source } => write!(f, "{}: {}", path.display(), source),
+            SkyboxError::NonSquareFace {
+                path,
+                width,
+                height,
+            } => write!(
+                f,
+                "{}: face is {}x{}, but faces have to be square",
+                path.display(),
+                width,
+                height
+            ),
+            SkyboxError::MismatchedSize {
+                path,
+                expected,
+                width,
+                height,
+            } => write!(
+                f,
+                "{}: face is {}x{}, but the other faces are {}x{}",
+                path.display(),
+                width,
+                height,
+                expected,
+                expected
+            ),
+            SkyboxError::ZeroFaceSize { path } => {
+                write!(f, "{}: faces need a size of at least 1", path.display())
+            }
+            SkyboxError::BadCrossSize {
+                path,
+                layout,
+                width,
+                height,
+            } => write!(
+                f,
+                "{}: {}x{} image does not split into a {:?} cross of square faces",
+                path.display(),
+                width,
+                height,
+                layout
+            ),
+            SkyboxError::Texture(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl std::error::Error for SkyboxError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            SkyboxError::Image { source, .. } => Some(source),
+            SkyboxError::Texture(source) => Some(source),
+            _ => None,
+        }
+    }
+}
+
+fn open_image(path: &Path) -> Result<DynamicImage, SkyboxError> {
+    image::open(path).map_err(|source| SkyboxError::Image {
+        path: path.to_owned(),
+        source,
+    })
+}
+
+// A face of the cube in the frame the source images were authored in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SourceFace {
+    PosX,
+    NegX,
+    PosY,
+    NegY,
+    PosZ,
+    NegZ,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum FaceRotation {
+    #[default]
+    None,
+    Cw90,
+    Cw180,
+    Cw270,
+}
+
+// Flips are applied first, then the clockwise rotation.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct FaceTransform {
+    pub flip_horizontal: bool,
+    pub flip_vertical: bool,
+    pub rotation: FaceRotation,
+}
+
+impl FaceTransform {
+    pub const NONE: FaceTransform = FaceTransform {
+        flip_horizontal: false,
+        flip_vertical: false,
+        rotation: FaceRotation::None,
+    };
+    pub const FLIP_VERTICAL: FaceTransform = FaceTransform {
+        flip_horizontal: false,
+        flip_vertical: true,
+        rotation: FaceRotation::None,
+    };
+    pub const ROTATE_180: FaceTransform = FaceTransform {
+        flip_horizontal: false,
+        flip_vertical: false,
+        rotation: FaceRotation::Cw180,
+    };
+
+    pub fn apply(&self, image: DynamicImage) -> DynamicImage {
+        let mut im = image;
+        if self.flip_horizontal {
+            im = im.fliph();
+        }
+        if self.flip_vertical {
+            im = im.flipv();
+        }
+        match self.rotation {
+            FaceRotation::None => im,
+            FaceRotation::Cw90 => im.rotate90(),
+            FaceRotation::Cw180 => im.rotate180(),
+            FaceRotation::Cw270 => im.rotate270(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct FaceSpec {
+    pub source: SourceFace,
+    pub transform: FaceTransform,
+}
+
+// Where each cube layer (+X, -X, +Y, -Y, +Z, -Z, in our frame where up is -Y)
+// comes from, and how the source image has to be turned to get there.
+#[derive(Debug, Clone, Copy)]
+pub struct CubemapConvention {
+    pub faces: [FaceSpec; 6],
+}
+
+impl CubemapConvention {
+    // Left-handed, +Y up, +Z forward. Mirroring y takes it to our frame.
+    // This is what `load_from_path` has always assumed.
+    pub fn directx() -> Self {
+        let flip = |source| FaceSpec {
+            source,
+            transform: FaceTransform::FLIP_VERTICAL,
+        };
+        CubemapConvention {
+            faces: [
+                flip(SourceFace::PosX),
+                flip(SourceFace::NegX),
+                flip(SourceFace::NegY),
+                flip(SourceFace::PosY),
+                flip(SourceFace::PosZ),
+                flip(SourceFace::NegZ),
+            ],
+        }
+    }
+
+    // Right-handed, +Y up, -Z forward. A half turn about X takes it to our frame.
+    pub fn opengl() -> Self {
+        let spec = |source, transform| FaceSpec { source, transform };
+        CubemapConvention {
+            faces: [
+                spec(SourceFace::PosX, FaceTransform::ROTATE_180),
+                spec(SourceFace::NegX, FaceTransform::ROTATE_180),
+                spec(SourceFace::NegY, FaceTransform::NONE),
+                spec(SourceFace::PosY, FaceTransform::NONE),
+                spec(SourceFace::NegZ, FaceTransform::ROTATE_180),
+                spec(SourceFace::PosZ, FaceTransform::ROTATE_180),
+            ],
+        }
+    }
+
+    pub fn assemble(
+        &self,
+        mut source: impl FnMut(SourceFace) -> DynamicImage,
+    ) -> [DynamicImage; 6] {
+        self.faces
+            .map(|spec| spec.transform.apply(source(spec.source)))
+    }
+}
+
+impl Default for CubemapConvention {
+    fn default() -> Self {
+        Self::directx()
+    }
+}
+
+// File stems for +X, -X, +Y, -Y, +Z, -Z in the source frame.
+#[derive(Debug, Clone)]
+pub struct FaceNames {
+    pub stems: [String; 6],
+    pub extension: String,
+}
+
+impl FaceNames {
+    fn new(stems: [&str; 6], extension: &str) -> Self {
+        FaceNames {
+            stems: stems.map(String::from),
+            extension: extension.into(),
+        }
+    }
+
+    pub fn right_left(extension: &str) -> Self {
+        Self::new(
+            ["right", "left", "top", "bottom", "front", "back"],
+            extension,
+        )
+    }
+
+    pub fn posx_negx(extension: &str) -> Self {
+        Self::new(["posx", "negx", "posy", "negy", "posz", "negz"], extension)
+    }
+
+    pub fn px_nx(extension: &str) -> Self {
+        Self::new(["px", "nx", "py", "ny", "pz", "nz"], extension)
+    }
+
+    pub fn file_name(&self, face: SourceFace) -> String {
+        format!("{}.{}", self.stems[face as usize], self.extension)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CrossLayout {
+    // 4x3 cells: +Y on top, then -X +Z +X -Z, then -Y
+    Horizontal,
+    // 3x4 cells: +Y on top, then -X +Z +X, then -Y, then -Z upside down
+    Vertical,
+}
+
+impl CrossLayout {
+    // Cell column and row, and what it takes to stand the cell upright
+    fn cell(&self, face: SourceFace) -> (u32, u32, FaceTransform) {
+        match (self, face) {
+            (CrossLayout::Horizontal, SourceFace::PosY) => (1, 0, FaceTransform::NONE),
+            (CrossLayout::Horizontal, SourceFace::NegX) => (0, 1, FaceTransform::NONE),
+            (CrossLayout::Horizontal, SourceFace::PosZ) => (1, 1, FaceTransform::NONE),
+            (CrossLayout::Horizontal, SourceFace::PosX) => (2, 1, FaceTransform::NONE),
+            (CrossLayout::Horizontal, SourceFace::NegZ) => (3, 1, FaceTransform::NONE),
+            (CrossLayout::Horizontal, SourceFace::NegY) => (1, 2, FaceTransform::NONE),
+            (CrossLayout::Vertical, SourceFace::PosY) => (1, 0, FaceTransform::NONE),
+            (CrossLayout::Vertical, SourceFace::NegX) => (0, 1, FaceTransform::NONE),
+            (CrossLayout::Vertical, SourceFace::PosZ) => (1, 1, FaceTransform::NONE),
+            (CrossLayout::Vertical, SourceFace::PosX) => (2, 1, FaceTransform::NONE),
+            (CrossLayout::Vertical, SourceFace::NegY) => (1, 2, FaceTransform::NONE),
+            (CrossLayout::Vertical, SourceFace::NegZ) => (1, 3, FaceTransform::ROTATE_180),
+        }
+    }
+
+    fn grid(&self) -> (u32, u32) {
+        match self {
+            CrossLayout::Horizontal => (4, 3),
+            CrossLayout::Vertical => (3, 4),
+        }
+    }
+}
+
+// Has to have equal resolution on all faces, which the loaders check.
+// Faces are stored in layer order +X, -X, +Y, -Y, +Z, -Z.
+pub struct RgbaSkybox {
+    data: TextureData,
+    dirty: Cell<bool>,
+}
+
+impl RgbaSkybox {
+    pub fn from_faces(
+        faces: &[DynamicImage; 6],
+        format: TextureFormat,
+    ) -> Result<Self, TextureError> {
+        Ok(RgbaSkybox {
+            data: TextureData::from_images(faces, format)?,
+            dirty: Cell::new(true),
+        })
+    }
+    pub fn with_mips(self, filter: MipFilter) -> Self {
+        RgbaSkybox {
+            data: self.data.with_mips(filter),
+            dirty: Cell::new(true),
+        }
+    }
+    pub fn dimensions(&self) -> (u32, u32) {
+        (self.width(), self.height())
+    }
+    pub fn width(&self) -> u32 {
+        self.data.size().width
+    }
+    pub fn height(&self) -> u32 {
+        self.data.size().height
+    }
+    pub fn data(&self) -> &TextureData {
+        &self.data
+    }
+    // right.png, left.png, ... in the DirectX convention
+    pub fn load_from_path(bg_path: &Path) -> Result<Self, SkyboxError> {
+        Self::load_faces(
+            bg_path,
+            &FaceNames::right_left("png"),
+            &CubemapConvention::default(),
+        )
+    }
+    pub fn load_faces(
+        bg_path: &Path,
+        names: &FaceNames,
+        convention: &CubemapConvention,
+    ) -> Result<Self, SkyboxError> {
+        let mut sources = Vec::with_capacity(6);
+        let mut expected = None;
+        for face in [
+            SourceFace::PosX,
+            SourceFace::NegX,
+            SourceFace::PosY,
+            SourceFace::NegY,
+            SourceFace::PosZ,
+            SourceFace::NegZ,
+        ] {
+            let path = bg_path.join(names.file_name(face));
+            if !path.is_file() {
+                return Err(SkyboxError::MissingFace { face, path });
+            }
+            let image = open_image(&path)?;
+            let (width, height) = (image.width(), image.height());
+            if width != height {
+                return Err(SkyboxError::NonSquareFace {
+                    path,
+                    width,
+                    height,
+                });
+            }
+            let expected = *expected.get_or_insert(width);
+            if width != expected {
+                return Err(SkyboxError::MismatchedSize {
+                    path,
+                    expected,
+                    width,
+                    height,
+                });
+            }
+            sources.push(image);
+        }
+        let faces = convention.assemble(|face| sources[face as usize].clone());
+        let format = default_format(&faces[0]);
+        Ok(Self::from_faces(&faces, format)?)
+    }
+    // A single image with all six faces laid out as a cross
+    pub fn load_cross(
+        path: &Path,
+        layout: CrossLayout,
+        convention: &CubemapConvention,
+    ) -> Result<Self, SkyboxError> {
+        let cross = open_image(path)?;
+        let (columns, rows) = layout.grid();
+        let face_size = cross.width() / columns;
+        if face_size == 0
+            || cross.width() != face_size * columns
+            || cross.height() != face_size * rows
+        {
+            return Err(SkyboxError::BadCrossSize {
+                path: path.to_owned(),
+                layout,
+                width: cross.width(),
+                height: cross.height(),
+            });
+        }
+        let faces = convention.assemble(|face| {
+            let (column, row, upright) = layout.cell(face);
+            upright.apply(cross.crop_imm(column * face_size, row * face_size, face_size, face_size))
+        });
+        let format = default_format(&faces[0]);
+        Ok(Self::from_faces(&faces, format)?)
+    }
+    // Resamples a single equirectangular panorama (.hdr, .exr, or anything else `image`
+    // reads) into six face_size x face_size faces. Stored as half floats so HDR
+    // sources keep their range. Up is -Y, same as the faces `load_from_path` reads.
+    pub fn load_equirectangular(path: &Path, face_size: u32) -> Result<Self, SkyboxError> {
+        if face_size == 0 {
+            return Err(SkyboxError::ZeroFaceSize {
+                path: path.to_owned(),
+            });
+        }
+        let panorama = image_as_rgba32f(&open_image(path)?, TextureFormat::Rgba16Float);
+        let faces = [0, 1, 2, 3, 4, 5].map(|face| {
+            let im = Rgba32FImage::from_fn(face_size, face_size, |x, y| {
+                let u = 2.0 * (x as f32 + 0.5) / face_size as f32 - 1.0;
+                let v = 2.0 * (y as f32 + 0.5) / face_size as f32 - 1.0;
+                sample_equirectangular(&panorama, cube_face_direction(face, u, v))
+            });
+            DynamicImage::ImageRgba32F(im)
+        });
+        Ok(Self::from_faces(&faces, TextureFormat::Rgba16Float)?)
+    }
+}
+
+// Direction through face-local coordinates u, v in [-1, 1] (u to the right, v down),
+// following the usual cube map layout the GPU samples with.
+pub fn cube_face_direction(face: usize, u: f32, v: f32) -> Vector3<f32> {
+    let d = match face {
+        0 => Vector3::new(1.0, -v, -u),
+        1 => Vector3::new(-1.0, -v, u),
+        2 => Vector3::new(u, 1.0, v),
+        3 => Vector3::new(u, -1.0, -v),
+        4 => Vector3::new(u, -v, 1.0),
+        5 => Vector3::new(-u, -v, -1.0),
+        _ => panic!("cube maps have six faces, got face {}", face),
+    };
+    d.normalize()
+}
+
+// Bilinear, wrapping around in longitude and clamping at the poles.
+fn sample_equirectangular(panorama: &Rgba32FImage, dir: Vector3<f32>) -> Rgba<f32> {
+    let (width, height) = panorama.dimensions();
+    let longitude = dir.x.atan2(dir.z);
+    let latitude = (-dir.y).clamp(-1.0, 1.0).asin();
+    let px = (0.5 + longitude / TAU) * width as f32 - 0.5;
+    let py = (0.5 - latitude / PI) * height as f32 - 0.5;
+    let x0 = px.floor();
+    let y0 = py.floor();
+    let fx = px - x0;
+    let fy = py - y0;
+    let wrap_x = |x: f32| (x as i64).rem_euclid(width as i64) as u32;
+    let clamp_y = |y: f32| (y as i64).clamp(0, height as i64 - 1) as u32;
+    let mut out = [0f32; 4];
+    for (sx, sy, w) in [
+        (x0, y0, (1.0 - fx) * (1.0 - fy)),
+        (x0 + 1.0, y0, fx * (1.0 - fy)),
+        (x0, y0 + 1.0, (1.0 - fx) * fy),
+        (x0 + 1.0, y0 + 1.0, fx * fy),
+    ] {
+        let p = panorama.get_pixel(wrap_x(sx), clamp_y(sy));
+        for (acc, ch) in out.iter_mut().zip(p.0) {
+            *acc += w * ch;
+        }
+    }
+    Rgba(out)
+}
+
+impl Writable for RgbaSkybox {
+    fn write_at_cursor(
+        &self,
+        c: Cursor,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        binding_resources: &mut BindingResources,
+    ) {
+        check_view_dimension(&c, TextureViewDimension::Cube);
+        upload_texture(
+            &self.data,
+            (TextureDimension::D2, TextureViewDimension::Cube),
+            &self.dirty,
+            c,
+            device,
+            queue,
+            binding_resources,
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use half::f16;
+    use image::RgbaImage;
+
+    use super::*;
+
+    const FACES: [SourceFace; 6] = [
+        SourceFace::PosX,
+        SourceFace::NegX,
+        SourceFace::PosY,
+        SourceFace::NegY,
+        SourceFace::PosZ,
+        SourceFace::NegZ,
+    ];
+
+    fn identity() -> CubemapConvention {
+        CubemapConvention {
+            faces: FACES.map(|source| FaceSpec {
+                source,
+                transform: FaceTransform::NONE,
+            }),
+        }
+    }
+
+    fn color(face: SourceFace) -> image::Rgba<u8> {
+        image::Rgba([40 * face as u8, 0, 0, 255])
+    }
+
+    // Every cell of the cross filled with its face's color, with a white texel
+    // in the top left corner of the cell
+    fn save_cross(name: &str, layout: CrossLayout, face_size: u32) -> PathBuf {
+        let (columns, rows) = layout.grid();
+        let mut cross = RgbaImage::new(columns * face_size, rows * face_size);
+        for face in FACES {
+            let (column, row, _) = layout.cell(face);
+            for y in 0..face_size {
+                for x in 0..face_size {
+                    let corner = x == 0 && y == 0;
+                    let texel = if corner {
+                        image::Rgba([255; 4])
+                    } else {
+                        color(face)
+                    };
+                    cross.put_pixel(column * face_size + x, row * face_size + y, texel);
+                }
+            }
+        }
+        let path = std::env::temp_dir().join(format!("wgpu_tidy_{}.png", name));
+        cross.save(&path).unwrap();
+        path
+    }
+
+    // Texel (x, y) of every layer, as stored
+    fn texels(skybox: &RgbaSkybox, x: u32, y: u32) -> Vec<[u8; 4]> {
+        let layer_bytes = 4 * (skybox.width() * skybox.height()) as usize;
+        let offset = 4 * (y * skybox.width() + x) as usize;
+        skybox.data().levels()[0]
+            .chunks(layer_bytes)
+            .map(|layer| layer[offset..offset + 4].try_into().unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn horizontal_cross_is_sliced_into_faces() {
+        let path = save_cross("horizontal_cross", CrossLayout::Horizontal, 3);
+        let skybox = RgbaSkybox::load_cross(&path, CrossLayout::Horizontal, &identity()).unwrap();
+        assert_eq!(skybox.dimensions(), (3, 3));
+        let expected: Vec<[u8; 4]> = FACES.iter().map(|&face| color(face).0).collect();
+        assert_eq!(texels(&skybox, 1, 1), expected);
+        assert!(texels(&skybox, 0, 0).iter().all(|&t| t == [255; 4]));
+    }
+
+    #[test]
+    fn vertical_cross_turns_the_back_face_upright() {
+        let path = save_cross("vertical_cross", CrossLayout::Vertical, 2);
+        let skybox = RgbaSkybox::load_cross(&path, CrossLayout::Vertical, &identity()).unwrap();
+        let top_left = texels(&skybox, 0, 0);
+        let bottom_right = texels(&skybox, 1, 1);
+        for (layer, face) in FACES.iter().enumerate() {
+            let upside_down = *face == SourceFace::NegZ;
+            assert_eq!(top_left[layer] == [255; 4], !upside_down, "{:?}", face);
+            assert_eq!(bottom_right[layer] == [255; 4], upside_down, "{:?}", face);
+        }
+    }
+
+    #[test]
+    fn cross_has_to_split_into_square_cells() {
+        let path = std::env::temp_dir().join("wgpu_tidy_bad_cross.png");
+        RgbaImage::new(10, 6).save(&path).unwrap();
+        assert!(matches!(
+            RgbaSkybox::load_cross(&path, CrossLayout::Horizontal, &identity()),
+            Err(SkyboxError::BadCrossSize {
+                width: 10,
+                height: 6,
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn directx_swaps_up_and_down_and_flips() {
+        let faces = CubemapConvention::directx().assemble(|face| {
+            let mut im = RgbaImage::from_pixel(1, 2, color(face));
+            im.put_pixel(0, 0, image::Rgba([255; 4]));
+            DynamicImage::ImageRgba8(im)
+        });
+        let up = faces[2].to_rgba8();
+        assert_eq!(*up.get_pixel(0, 0), color(SourceFace::NegY));
+        assert_eq!(*up.get_pixel(0, 1), image::Rgba([255; 4]));
+    }
+
+    #[test]
+    fn transforms_flip_before_rotating() {
+        let im = DynamicImage::ImageRgba8(RgbaImage::from_fn(2, 1, |x, _| {
+            image::Rgba([x as u8, 0, 0, 255])
+        }));
+        let transform = FaceTransform {
+            flip_horizontal: true,
+            flip_vertical: false,
+            rotation: FaceRotation::Cw90,
+        };
+        let out = transform.apply(im).to_rgba8();
+        assert_eq!(out.dimensions(), (1, 2));
+        assert_eq!(out.get_pixel(0, 0).0[0], 1);
+        assert_eq!(out.get_pixel(0, 1).0[0], 0);
+    }
+
+    #[test]
+    fn face_centers_point_along_the_axes() {
+        let axes = [
+            Vector3::unit_x(),
+            -Vector3::unit_x(),
+            Vector3::unit_y(),
+            -Vector3::unit_y(),
+            Vector3::unit_z(),
+            -Vector3::unit_z(),
+        ];
+        for (face, axis) in axes.into_iter().enumerate() {
+            assert!((cube_face_direction(face, 0.0, 0.0) - axis).magnitude() < 1e-6);
+        }
+    }
+
+    #[test]
+    fn equirectangular_needs_a_face_size() {
+        assert!(matches!(
+            RgbaSkybox::load_equirectangular(Path::new("missing.hdr"), 0),
+            Err(SkyboxError::ZeroFaceSize { .. })
+        ));
+    }
+
+    // Red channel of texel (x, y) of every layer of a half-float skybox
+    fn red_texels(skybox: &RgbaSkybox, x: u32, y: u32) -> Vec<f32> {
+        let layer_bytes = 8 * (skybox.width() * skybox.height()) as usize;
+        let offset = 8 * (y * skybox.width() + x) as usize;
+        skybox.data().levels()[0]
+            .chunks(layer_bytes)
+            .map(|layer| f16::from_le_bytes([layer[offset], layer[offset + 1]]).to_f32())
+            .collect()
+    }
+
+    fn save_panorama(name: &str, panorama: RgbaImage) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("wgpu_tidy_{}.png", name));
+        panorama.save(&path).unwrap();
+        path
+    }
+
+    #[test]
+    fn panorama_top_rows_land_on_the_up_face() {
+        let path = save_panorama(
+            "panorama_top",
+            RgbaImage::from_fn(8, 4, |_, y| image::Rgba([255 * (y == 0) as u8, 0, 0, 255])),
+        );
+        let skybox = RgbaSkybox::load_equirectangular(&path, 4).unwrap();
+        // Up is -Y in our frame, which is layer 3
+        for (x, y) in [(1, 1), (2, 1), (1, 2), (2, 2)] {
+            assert_eq!(red_texels(&skybox, x, y), [0.0, 0.0, 0.0, 1.0, 0.0, 0.0]);
+        }
+    }
+
+    #[test]
+    fn panorama_center_lands_on_the_front_face_center() {
+        // Longitude 0 is the middle of the panorama, a quarter turn on is +X
+        let path = save_panorama(
+            "panorama_longitude",
+            RgbaImage::from_fn(8, 4, |x, _| {
+                let red = match x {
+                    3 | 4 => 255,
+                    5 | 6 => 51,
+                    _ => 0,
+                };
+                image::Rgba([red, 0, 0, 255])
+            }),
+        );
+        let skybox = RgbaSkybox::load_equirectangular(&path, 1).unwrap();
+        let red = red_texels(&skybox, 0, 0);
+        assert_eq!(red[4], 1.0);
+        assert!(red[0] > 0.0 && red[0] < 1.0);
+        assert_eq!([red[1], red[5]], [0.0, 0.0]);
+    }
+}
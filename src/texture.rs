@@ -0,0 +1,964 @@
+// Texture Writables backed by host-side texel data.
+// The GPU texture is (re)created whenever the reflected slot is empty or the
+// data changed size, format or mip count, and the texels are uploaded whenever
+// the texture is fresh or the data was replaced since the last write.
+// StorageTexture2D and RenderTarget2D are the exceptions, their texels only ever come from
+// shaders.
+
+use std::{
+    cell::{Cell, RefCell},
+    fmt,
+    marker::PhantomData,
+};
+
+use half::f16;
+use image::{DynamicImage, Rgba32FImage, imageops::FilterType};
+use wgpu::{
+    Extent3d, Origin3d, TexelCopyBufferLayout, TexelCopyTextureInfo, TextureAspect,
+    TextureDescriptor, TextureDimension, TextureFormat, TextureUsages, TextureViewDescriptor,
+    TextureViewDimension,
+};
+
+use crate::reflection::{BindingResources, Cursor, Writable, view_dimension_of};
+
+pub trait TextureShape {
+    const DIMENSION: TextureDimension;
+    const VIEW_DIMENSION: TextureViewDimension;
+}
+
+pub struct Shape1D;
+pub struct Shape2D;
+pub struct Shape3D;
+pub struct Shape2DArray;
+
+impl TextureShape for Shape1D {
+    const DIMENSION: TextureDimension = TextureDimension::D1;
+    const VIEW_DIMENSION: TextureViewDimension = TextureViewDimension::D1;
+}
+
+impl TextureShape for Shape2D {
+    const DIMENSION: TextureDimension = TextureDimension::D2;
+    const VIEW_DIMENSION: TextureViewDimension = TextureViewDimension::D2;
+}
+
+impl TextureShape for Shape3D {
+    const DIMENSION: TextureDimension = TextureDimension::D3;
+    const VIEW_DIMENSION: TextureViewDimension = TextureViewDimension::D3;
+}
+
+impl TextureShape for Shape2DArray {
+    const DIMENSION: TextureDimension = TextureDimension::D2;
+    const VIEW_DIMENSION: TextureViewDimension = TextureViewDimension::D2Array;
+}
+
+pub type Texture1D = Texture<Shape1D>;
+pub type Texture2D = Texture<Shape2D>;
+pub type Texture3D = Texture<Shape3D>;
+pub type Texture2DArray = Texture<Shape2DArray>;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MipFilter {
+    // 2x2 average of the previous level
+    Box,
+    Lanczos,
+}
+
+#[derive(Debug)]
+pub enum TextureError {
+    UnsupportedFormat(TextureFormat),
+    NoImages,
+    MismatchedLayers {
+        layer: usize,
+        expected: (u32, u32),
+        found: (u32, u32),
+    },
+    WrongTexelCount {
+        format: TextureFormat,
+        size: Extent3d,
+        expected_bytes: usize,
+        found_bytes: usize,
+    },
+    NotOneDimensional {
+        size: Extent3d,
+    },
+    UnsupportedMips {
+        dimension: TextureDimension,
+    },
+}
+
+impl fmt::Display for TextureError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TextureError::UnsupportedFormat(format) => {
+                write!(f, "unsupported texture format {:?}", format)
+            }
+            TextureError::NoImages => write!(f, "need at least one image"),
+            TextureError::MismatchedLayers {
+                layer,
+                expected,
+                found,
+            } => write!(
+                f,
+                "layer {} is {}x{}, but the first layer is {}x{}",
+                layer, found.0, found.1, expected.0, expected.1
+            ),
+            TextureError::WrongTexelCount {
+                format,
+                size,
+                expected_bytes,
+                found_bytes,
+            } => write!(
+                f,
+                "{} bytes of texel data do not fit a {:?} texture of size {}x{}x{}, which needs {}",
+                found_bytes,
+                format,
+                size.width,
+                size.height,
+                size.depth_or_array_layers,
+                expected_bytes
+            ),
+            TextureError::NotOneDimensional { size } => write!(
+                f,
+                "1D textures must have height and depth 1, got {}x{}x{}",
+                size.width, size.height, size.depth_or_array_layers
+            ),
+            TextureError::UnsupportedMips { dimension } => {
+                write!(f, "mipmaps are not supported for {:?} textures", dimension)
+            }
+        }
+    }
+}
+
+impl std::error::Error for TextureError {}
+
+// Tightly packed texels, layer (or depth slice) after layer, one Vec per mip level.
+#[derive(Clone)]
+pub struct TextureData {
+    format: TextureFormat,
+    size: Extent3d,
+    levels: Vec<Vec<u8>>,
+}
+
+impl TextureData {
+    pub fn from_texels<T: bytemuck::Pod>(
+        format: TextureFormat,
+        size: Extent3d,
+        texels: &[T],
+    ) -> Result<Self, TextureError> {
+        let bytes: &[u8] = bytemuck::cast_slice(texels);
+        let expected = bytes_per_texel(format)? as usize
+            * size.width as usize
+            * size.height as usize
+            * size.depth_or_array_layers as usize;
+        if bytes.len() != expected {
+            return Err(TextureError::WrongTexelCount {
+                format,
+                size,
+                expected_bytes: expected,
+                found_bytes: bytes.len(),
+            });
+        }
+        Ok(TextureData {
+            format,
+            size,
+            levels: vec![bytes.to_vec()],
+        })
+    }
+
+    // All images must have the same dimensions. Each one becomes a layer.
+    pub fn from_images(
+        images: &[DynamicImage],
+        format: TextureFormat,
+    ) -> Result<Self, TextureError> {
+        let first = images.first().ok_or(TextureError::NoImages)?;
+        let (width, height) = (first.width(), first.height());
+        let mut texels = Vec::new();
+        for (layer, im) in images.iter().enumerate() {
+            if (im.width(), im.height()) != (width, height) {
+                return Err(TextureError::MismatchedLayers {
+                    layer,
+                    expected: (width, height),
+                    found: (im.width(), im.height()),
+                });
+            }
+            texels.extend(encode_texels(format, &image_as_rgba32f(im, format))?);
+        }
+        Ok(TextureData {
+            format,
+            size: Extent3d {
+                width,
+                height,
+                depth_or_array_layers: images.len().try_into().unwrap(),
+            },
+            levels: vec![texels],
+        })
+    }
+
+    // Replaces any existing mip chain with a full one down to 1x1, built from level 0.
+    // Layers are filtered independently, so this is for 2D, 2D array and cube data.
+    // sRGB data is filtered in linear space.
+    pub(crate) fn with_mips(self, filter: MipFilter) -> Self {
+        let format = self.format;
+        let (width, height) = (self.size.width, self.size.height);
+        let layer_count = self.size.depth_or_array_layers as usize;
+        let layer_bytes = self.levels[0].len() / layer_count;
+        let mut layers: Vec<Rgba32FImage> = self.levels[0]
+            .chunks(layer_bytes)
+            .map(|bytes| {
+                let mut im = decode_texels(format, width, height, bytes).expect(SUPPORTED);
+                if format.is_srgb() {
+                    map_rgb(&mut im, srgb_to_linear);
+                }
+                im
+            })
+            .collect();
+        let mut levels = vec![self.levels[0].clone()];
+        for level in 1..self.size.max_mips(TextureDimension::D2) {
+            let level_size = self.size.mip_level_size(level, TextureDimension::D2);
+            layers = layers
+                .iter()
+                .map(|im| downsample(im, level_size.width, level_size.height, filter))
+                .collect();
+            let mut texels = Vec::new();
+            for im in &layers {
+                if format.is_srgb() {
+                    let mut stored = im.clone();
+                    map_rgb(&mut stored, linear_to_srgb);
+                    texels.extend(encode_texels(format, &stored).expect(SUPPORTED));
+                } else {
+                    texels.extend(encode_texels(format, im).expect(SUPPORTED));
+                }
+            }
+            levels.push(texels);
+        }
+        TextureData {
+            format,
+            size: self.size,
+            levels,
+        }
+    }
+
+    pub fn format(&self) -> TextureFormat {
+        self.format
+    }
+
+    pub fn size(&self) -> Extent3d {
+        self.size
+    }
+
+    pub fn mip_level_count(&self) -> u32 {
+        self.levels.len().try_into().unwrap()
+    }
+
+    pub fn levels(&self) -> &[Vec<u8>] {
+        &self.levels
+    }
+}
+
+pub struct Texture<S: TextureShape> {
+    data: TextureData,
+    dirty: Cell<bool>,
+    shape: PhantomData<S>,
+}
+
+impl<S: TextureShape> Texture<S> {
+    pub fn from_data(data: TextureData) -> Result<Self, TextureError> {
+        if S::DIMENSION == TextureDimension::D1
+            && (data.size.height != 1 || data.size.depth_or_array_layers != 1)
+        {
+            return Err(TextureError::NotOneDimensional { size: data.size });
+        }
+        if S::DIMENSION != TextureDimension::D2 && data.mip_level_count() != 1 {
+            return Err(TextureError::UnsupportedMips {
+                dimension: S::DIMENSION,
+            });
+        }
+        Ok(Texture {
+            data,
+            dirty: Cell::new(true),
+            shape: PhantomData,
+        })
+    }
+
+    pub fn from_texels<T: bytemuck::Pod>(
+        format: TextureFormat,
+        size: Extent3d,
+        texels: &[T],
+    ) -> Result<Self, TextureError> {
+        Self::from_data(TextureData::from_texels(format, size, texels)?)
+    }
+
+    pub fn data(&self) -> &TextureData {
+        &self.data
+    }
+
+    fn mipmapped(self, filter: MipFilter) -> Self {
+        Texture {
+            data: self.data.with_mips(filter),
+            dirty: Cell::new(true),
+            shape: PhantomData,
+        }
+    }
+
+    // Takes effect on the next write, recreating the texture if needed.
+    // On error the texture keeps its old data.
+    pub fn set_data(&mut self, data: TextureData) -> Result<(), TextureError> {
+        *self = Self::from_data(data)?;
+        Ok(())
+    }
+}
+
+impl Texture1D {
+    // The image has to be a single row.
+    pub fn from_image(image: &DynamicImage) -> Result<Self, TextureError> {
+        Self::from_image_as(image, default_format(image))
+    }
+
+    pub fn from_image_as(
+        image: &DynamicImage,
+        format: TextureFormat,
+    ) -> Result<Self, TextureError> {
+        Self::from_data(TextureData::from_images(
+            std::slice::from_ref(image),
+            format,
+        )?)
+    }
+}
+
+impl Texture2D {
+    pub fn from_image(image: &DynamicImage) -> Result<Self, TextureError> {
+        Self::from_image_as(image, default_format(image))
+    }
+
+    pub fn from_image_as(
+        image: &DynamicImage,
+        format: TextureFormat,
+    ) -> Result<Self, TextureError> {
+        Self::from_data(TextureData::from_images(
+            std::slice::from_ref(image),
+            format,
+        )?)
+    }
+
+    pub fn with_mips(self, filter: MipFilter) -> Self {
+        self.mipmapped(filter)
+    }
+}
+
+impl Texture2DArray {
+    pub fn from_images(layers: &[DynamicImage]) -> Result<Self, TextureError> {
+        let first = layers.first().ok_or(TextureError::NoImages)?;
+        Self::from_images_as(layers, default_format(first))
+    }
+
+    pub fn from_images_as(
+        layers: &[DynamicImage],
+        format: TextureFormat,
+    ) -> Result<Self, TextureError> {
+        Self::from_data(TextureData::from_images(layers, format)?)
+    }
+
+    // Each layer gets its own chain
+    pub fn with_mips(self, filter: MipFilter) -> Self {
+        self.mipmapped(filter)
+    }
+}
+
+impl Texture3D {
+    // Each image is one depth slice.
+    pub fn from_images(slices: &[DynamicImage]) -> Result<Self, TextureError> {
+        let first = slices.first().ok_or(TextureError::NoImages)?;
+        Self::from_images_as(slices, default_format(first))
+    }
+
+    pub fn from_images_as(
+        slices: &[DynamicImage],
+        format: TextureFormat,
+    ) -> Result<Self, TextureError> {
+        Self::from_data(TextureData::from_images(slices, format)?)
+    }
+}
+
+impl<S: TextureShape> Writable for Texture<S> {
+    fn write_at_cursor(
+        &self,
+        c: Cursor,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        binding_resources: &mut BindingResources,
+    ) {
+        check_view_dimension(&c, S::VIEW_DIMENSION);
+        upload_texture(
+            &self.data,
+            (S::DIMENSION, S::VIEW_DIMENSION),
+            &self.dirty,
+            c,
+            device,
+            queue,
+            binding_resources,
+        );
+    }
+}
+
+// A 2D texture only shaders write, such as a compute shader's output.
+// The GPU texture is created on the first write after construction or a resize. Writing the same
+// StorageTexture2D into several programs binds the one texture in all of them, e.g. as an
+// RWTexture2D where it's written and a Texture2D where it's read.
+// `format` has to be the one the shader declares, e.g. `[format("rgba16f")]` for Rgba16Float.
+pub struct StorageTexture2D {
+    width: u32,
+    height: u32,
+    format: TextureFormat,
+    texture: RefCell<Option<wgpu::Texture>>,
+}
+
+impl StorageTexture2D {
+    pub fn new(width: u32, height: u32, format: TextureFormat) -> Self {
+        StorageTexture2D {
+            width,
+            height,
+            format,
+            texture: RefCell::new(None),
+        }
+    }
+
+    pub fn size(&self) -> (u32, u32) {
+        (self.width, self.height)
+    }
+
+    // Takes effect on the next write. The old contents are lost.
+    pub fn resize(&mut self, width: u32, height: u32) {
+        if (width, height) != (self.width, self.height) {
+            *self = Self::new(width, height, self.format);
+        }
+    }
+}
+
+impl Writable for StorageTexture2D {
+    fn write_at_cursor(
+        &self,
+        c: Cursor,
+        device: &wgpu::Device,
+        _queue: &wgpu::Queue,
+        binding_resources: &mut BindingResources,
+    ) {
+        check_view_dimension(&c, TextureViewDimension::D2);
+        let texture = self
+            .texture
+            .borrow_mut()
+            .get_or_insert_with(|| {
+                device.create_texture(&TextureDescriptor {
+                    label: Some("storage_texture"),
+                    size: Extent3d {
+                        width: self.width,
+                        height: self.height,
+                        depth_or_array_layers: 1,
+                    },
+                    mip_level_count: 1,
+                    sample_count: 1,
+                    dimension: TextureDimension::D2,
+                    format: self.format,
+                    usage: TextureUsages::STORAGE_BINDING
+                        | TextureUsages::TEXTURE_BINDING
+                        | TextureUsages::COPY_SRC,
+                    view_formats: &[],
+                })
+            })
+            .clone();
+        bind_texture(c, texture, binding_resources);
+    }
+}
+
+// A 2D texture a render pipeline draws into, for later passes to sample, e.g. to upscale an
+// image rendered at reduced resolution. Created on first use like StorageTexture2D.
+pub struct RenderTarget2D {
+    width: u32,
+    height: u32,
+    format: TextureFormat,
+    texture: RefCell<Option<wgpu::Texture>>,
+}
+
+impl RenderTarget2D {
+    pub fn new(width: u32, height: u32, format: TextureFormat) -> Self {
+        RenderTarget2D {
+            width,
+            height,
+            format,
+            texture: RefCell::new(None),
+        }
+    }
+
+    pub fn size(&self) -> (u32, u32) {
+        (self.width, self.height)
+    }
+
+    // Takes effect on the next use. The old contents are lost.
+    pub fn resize(&mut self, width: u32, height: u32) {
+        if (width, height) != (self.width, self.height) {
+            *self = Self::new(width, height, self.format);
+        }
+    }
+
+    pub fn texture(&self, device: &wgpu::Device) -> wgpu::Texture {
+        self.texture
+            .borrow_mut()
+            .get_or_insert_with(|| {
+                device.create_texture(&TextureDescriptor {
+                    label: Some("render_target"),
+                    size: Extent3d {
+                        width: self.width,
+                        height: self.height,
+                        depth_or_array_layers: 1,
+                    },
+                    mip_level_count: 1,
+                    sample_count: 1,
+                    dimension: TextureDimension::D2,
+                    format: self.format,
+                    usage: TextureUsages::RENDER_ATTACHMENT | TextureUsages::TEXTURE_BINDING,
+                    view_formats: &[],
+                })
+            })
+            .clone()
+    }
+
+    // For a render pass color attachment
+    pub fn view(&self, device: &wgpu::Device) -> wgpu::TextureView {
+        self.texture(device)
+            .create_view(&TextureViewDescriptor::default())
+    }
+}
+
+impl Writable for RenderTarget2D {
+    fn write_at_cursor(
+        &self,
+        c: Cursor,
+        device: &wgpu::Device,
+        _queue: &wgpu::Queue,
+        binding_resources: &mut BindingResources,
+    ) {
+        check_view_dimension(&c, TextureViewDimension::D2);
+        bind_texture(c, self.texture(device), binding_resources);
+    }
+}
+
+// Puts `texture` at the cursor's slot, unless it's there already
+fn bind_texture(c: Cursor, texture: wgpu::Texture, binding_resources: &mut BindingResources) {
+    let set_index = c.offset().set();
+    let slot_index = c.offset().slot();
+    let tset = binding_resources.textures.entry(set_index).or_default();
+    if tset.get(&slot_index) == Some(&texture) {
+        return;
+    }
+    let texture_view = texture.create_view(&TextureViewDescriptor::default());
+    tset.insert(slot_index, texture);
+    binding_resources
+        .texture_views
+        .entry(set_index)
+        .or_default()
+        .insert(slot_index, texture_view);
+}
+
+pub fn check_view_dimension(c: &Cursor, expected: TextureViewDimension) {
+    let reflected = c
+        .type_layout()
+        .ty()
+        .and_then(|ty| view_dimension_of(ty.resource_shape()));
+    assert_eq!(
+        reflected,
+        Some(expected),
+        "{:?} texture written to a shader resource of shape {:?}",
+        expected,
+        reflected
+    );
+}
+
+// The texture and view dimensions differ for cube maps, which are 2D textures with a cube view
+pub(crate) fn upload_texture(
+    data: &TextureData,
+    (dimension, view_dimension): (TextureDimension, TextureViewDimension),
+    dirty: &Cell<bool>,
+    c: Cursor,
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    binding_resources: &mut BindingResources,
+) {
+    let set_index = c.offset().set();
+    let slot_index = c.offset().slot();
+    let tset = binding_resources.textures.entry(set_index).or_default();
+    let tviewset = binding_resources.texture_views.entry(set_index).or_default();
+    let stale = tset.get(&slot_index).is_none_or(|tex| {
+        tex.size() != data.size()
+            || tex.format() != data.format()
+            || tex.dimension() != dimension
+            || tex.mip_level_count() != data.mip_level_count()
+    });
+    if stale {
+        let texture = device.create_texture(&TextureDescriptor {
+            label: None,
+            size: data.size(),
+            mip_level_count: data.mip_level_count(),
+            sample_count: 1,
+            dimension,
+            format: data.format(),
+            usage: TextureUsages::COPY_DST | TextureUsages::TEXTURE_BINDING,
+            view_formats: &[],
+        });
+        let texture_view = texture.create_view(&TextureViewDescriptor {
+            dimension: Some(view_dimension),
+            ..Default::default()
+        });
+        tset.insert(slot_index, texture);
+        tviewset.insert(slot_index, texture_view);
+    }
+    if !(stale || dirty.get()) {
+        return;
+    }
+    let tex = tset.get(&slot_index).unwrap();
+    for (level, texels) in data.levels().iter().enumerate() {
+        let level: u32 = level.try_into().unwrap();
+        let level_size = data.size().mip_level_size(level, dimension);
+        queue.write_texture(
+            TexelCopyTextureInfo {
+                texture: tex,
+                mip_level: level,
+                origin: Origin3d { x: 0, y: 0, z: 0 },
+                aspect: TextureAspect::All,
+            },
+            texels,
+            TexelCopyBufferLayout {
+                offset: 0,
+                bytes_per_row: Some(
+                    bytes_per_texel(data.format()).expect(SUPPORTED) * level_size.width,
+                ),
+                rows_per_image: Some(level_size.height),
+            },
+            level_size,
+        );
+    }
+    dirty.set(false);
+}
+
+// 8 bit images go to sRGB, anything with more precision goes to half floats
+// so it stays filterable.
+pub fn default_format(image: &DynamicImage) -> TextureFormat {
+    match image {
+        DynamicImage::ImageLuma16(_)
+        | DynamicImage::ImageLumaA16(_)
+        | DynamicImage::ImageRgb16(_)
+        | DynamicImage::ImageRgba16(_)
+        | DynamicImage::ImageRgb32F(_)
+        | DynamicImage::ImageRgba32F(_) => TextureFormat::Rgba16Float,
+        _ => TextureFormat::Rgba8UnormSrgb,
+    }
+}
+
+// R32Float and Rg32Float are only filterable on adapters that report it,
+// which is why the device asks for TEXTURE_ADAPTER_SPECIFIC_FORMAT_FEATURES.
+pub fn bytes_per_texel(format: TextureFormat) -> Result<u32, TextureError> {
+    match format {
+        TextureFormat::Rgba8Unorm
+        | TextureFormat::Rgba8UnormSrgb
+        | TextureFormat::Rgba16Float
+        | TextureFormat::R32Float
+        | TextureFormat::Rg32Float
+        | TextureFormat::Rgba32Float => Ok(format.block_copy_size(None).unwrap()),
+        _ => Err(TextureError::UnsupportedFormat(format)),
+    }
+}
+
+// TextureData can only be built in formats bytes_per_texel accepts
+const SUPPORTED: &str = "TextureData holds a supported format";
+
+// Integer images hold sRGB-encoded values, so they get linearized
+// when they are headed for a float format.
+pub fn image_as_rgba32f(image: &DynamicImage, format: TextureFormat) -> Rgba32FImage {
+    let mut out = image.to_rgba32f();
+    let is_float_image = matches!(
+        image,
+        DynamicImage::ImageRgb32F(_) | DynamicImage::ImageRgba32F(_)
+    );
+    let is_float_format = matches!(
+        format,
+        TextureFormat::Rgba16Float
+            | TextureFormat::R32Float
+            | TextureFormat::Rg32Float
+            | TextureFormat::Rgba32Float
+    );
+    if !is_float_image && is_float_format {
+        map_rgb(&mut out, srgb_to_linear);
+    }
+    out
+}
+
+fn map_rgb(image: &mut Rgba32FImage, f: fn(f32) -> f32) {
+    for p in image.pixels_mut() {
+        for ch in &mut p.0[0..3] {
+            *ch = f(*ch);
+        }
+    }
+}
+
+fn downsample(image: &Rgba32FImage, width: u32, height: u32, filter: MipFilter) -> Rgba32FImage {
+    match filter {
+        MipFilter::Lanczos => image::imageops::resize(image, width, height, FilterType::Lanczos3),
+        MipFilter::Box => Rgba32FImage::from_fn(width, height, |x, y| {
+            // Odd edges just reuse the last row/column
+            let x0 = (2 * x).min(image.width() - 1);
+            let y0 = (2 * y).min(image.height() - 1);
+            let x1 = (2 * x + 1).min(image.width() - 1);
+            let y1 = (2 * y + 1).min(image.height() - 1);
+            let mut sum = [0f32; 4];
+            for (sx, sy) in [(x0, y0), (x1, y0), (x0, y1), (x1, y1)] {
+                for (acc, ch) in sum.iter_mut().zip(image.get_pixel(sx, sy).0) {
+                    *acc += ch;
+                }
+            }
+            image::Rgba(sum.map(|x| x / 4.0))
+        }),
+    }
+}
+
+pub fn srgb_to_linear(x: f32) -> f32 {
+    if x <= 0.04045 {
+        x / 12.92
+    } else {
+        ((x + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+pub fn linear_to_srgb(x: f32) -> f32 {
+    if x <= 0.0031308 {
+        x * 12.92
+    } else {
+        1.055 * x.powf(1.0 / 2.4) - 0.055
+    }
+}
+
+pub fn decode_texels(
+    format: TextureFormat,
+    width: u32,
+    height: u32,
+    bytes: &[u8],
+) -> Result<Rgba32FImage, TextureError> {
+    let stride = bytes_per_texel(format)? as usize;
+    let expected_bytes = stride * (width * height) as usize;
+    if bytes.len() != expected_bytes {
+        return Err(TextureError::WrongTexelCount {
+            format,
+            size: Extent3d {
+                width,
+                height,
+                depth_or_array_layers: 1,
+            },
+            expected_bytes,
+            found_bytes: bytes.len(),
+        });
+    }
+    let mut out = Vec::with_capacity(4 * (width * height) as usize);
+    for texel in bytes.chunks(stride) {
+        let f32_at = |i: usize| f32::from_le_bytes(texel[4 * i..4 * i + 4].try_into().unwrap());
+        let rgba = match format {
+            TextureFormat::Rgba8Unorm | TextureFormat::Rgba8UnormSrgb => {
+                [0, 1, 2, 3].map(|i| texel[i] as f32 / 255.0)
+            }
+            TextureFormat::Rgba16Float => [0, 1, 2, 3]
+                .map(|i| f16::from_le_bytes([texel[2 * i], texel[2 * i + 1]]).to_f32()),
+            TextureFormat::R32Float => [f32_at(0), 0.0, 0.0, 1.0],
+            TextureFormat::Rg32Float => [f32_at(0), f32_at(1), 0.0, 1.0],
+            TextureFormat::Rgba32Float => [0, 1, 2, 3].map(f32_at),
+            _ => unreachable!(),
+        };
+        out.extend(rgba);
+    }
+    Ok(Rgba32FImage::from_raw(width, height, out).unwrap())
+}
+
+pub fn encode_texels(format: TextureFormat, image: &Rgba32FImage) -> Result<Vec<u8>, TextureError> {
+    let mut out = Vec::with_capacity(
+        bytes_per_texel(format)? as usize * (image.width() * image.height()) as usize,
+    );
+    for p in image.pixels() {
+        let [r, g, b, a] = p.0;
+        match format {
+            TextureFormat::Rgba8Unorm | TextureFormat::Rgba8UnormSrgb => {
+                out.extend([r, g, b, a].map(|x| (x.clamp(0.0, 1.0) * 255.0).round() as u8))
+            }
+            TextureFormat::Rgba16Float => {
+                for x in [r, g, b, a] {
+                    out.extend(f16::from_f32(x).to_le_bytes());
+                }
+            }
+            TextureFormat::R32Float => out.extend(r.to_le_bytes()),
+            TextureFormat::Rg32Float => {
+                out.extend(r.to_le_bytes());
+                out.extend(g.to_le_bytes());
+            }
+            TextureFormat::Rgba32Float => {
+                for x in [r, g, b, a] {
+                    out.extend(x.to_le_bytes());
+                }
+            }
+            _ => unreachable!(),
+        }
+    }
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rgba(width: u32, height: u32, f: impl Fn(u32, u32) -> [f32; 4]) -> Rgba32FImage {
+        Rgba32FImage::from_fn(width, height, |x, y| image::Rgba(f(x, y)))
+    }
+
+    #[test]
+    fn texels_round_trip() {
+        let image = rgba(3, 2, |x, y| [x as f32 / 2.0, y as f32, 0.25, 1.0]);
+        for format in [
+            TextureFormat::Rgba8Unorm,
+            TextureFormat::Rgba16Float,
+            TextureFormat::Rgba32Float,
+        ] {
+            let bytes = encode_texels(format, &image).unwrap();
+            assert_eq!(bytes.len(), 6 * bytes_per_texel(format).unwrap() as usize);
+            let decoded = decode_texels(format, 3, 2, &bytes).unwrap();
+            for (a, b) in image.pixels().zip(decoded.pixels()) {
+                for (a, b) in a.0.iter().zip(b.0) {
+                    assert!((a - b).abs() < 1.0 / 255.0, "{:?}: {} != {}", format, a, b);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn single_channel_formats_fill_in_the_rest() {
+        let image = rgba(1, 1, |_, _| [0.5, 0.75, 0.1, 0.2]);
+        let bytes = encode_texels(TextureFormat::Rg32Float, &image).unwrap();
+        let decoded = decode_texels(TextureFormat::Rg32Float, 1, 1, &bytes).unwrap();
+        assert_eq!(decoded.get_pixel(0, 0).0, [0.5, 0.75, 0.0, 1.0]);
+    }
+
+    #[test]
+    fn unsupported_format_is_an_error() {
+        let image = rgba(1, 1, |_, _| [0.0; 4]);
+        assert!(matches!(
+            encode_texels(TextureFormat::Bgra8Unorm, &image),
+            Err(TextureError::UnsupportedFormat(TextureFormat::Bgra8Unorm))
+        ));
+        assert!(matches!(
+            decode_texels(TextureFormat::R8Unorm, 1, 1, &[0]),
+            Err(TextureError::UnsupportedFormat(_))
+        ));
+    }
+
+    #[test]
+    fn truncated_texels_are_an_error() {
+        let bytes =
+            encode_texels(TextureFormat::Rgba16Float, &rgba(2, 2, |_, _| [1.0; 4])).unwrap();
+        assert!(matches!(
+            decode_texels(TextureFormat::Rgba16Float, 2, 2, &bytes[..bytes.len() - 1]),
+            Err(TextureError::WrongTexelCount {
+                expected_bytes: 32,
+                found_bytes: 31,
+                ..
+            })
+        ));
+        assert!(decode_texels(TextureFormat::Rgba16Float, 2, 3, &bytes).is_err());
+    }
+
+    #[test]
+    fn texel_count_has_to_match_the_size() {
+        let size = Extent3d {
+            width: 2,
+            height: 2,
+            depth_or_array_layers: 1,
+        };
+        assert!(TextureData::from_texels(TextureFormat::R32Float, size, &[0f32; 4]).is_ok());
+        assert!(matches!(
+            TextureData::from_texels(TextureFormat::R32Float, size, &[0f32; 3]),
+            Err(TextureError::WrongTexelCount {
+                expected_bytes: 16,
+                found_bytes: 12,
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn layers_have_to_match() {
+        let a = DynamicImage::new_rgba8(2, 2);
+        let b = DynamicImage::new_rgba8(2, 3);
+        assert!(matches!(
+            Texture2DArray::from_images(&[]),
+            Err(TextureError::NoImages)
+        ));
+        assert!(matches!(
+            Texture2DArray::from_images(&[a.clone(), b]),
+            Err(TextureError::MismatchedLayers {
+                layer: 1,
+                found: (2, 3),
+                ..
+            })
+        ));
+        let array = Texture2DArray::from_images(&[a.clone(), a]).unwrap();
+        assert_eq!(array.data().size().depth_or_array_layers, 2);
+    }
+
+    #[test]
+    fn one_dimensional_textures_are_one_row() {
+        assert!(Texture1D::from_image(&DynamicImage::new_rgba8(4, 1)).is_ok());
+        assert!(matches!(
+            Texture1D::from_image(&DynamicImage::new_rgba8(4, 2)),
+            Err(TextureError::NotOneDimensional { .. })
+        ));
+    }
+
+    #[test]
+    fn mips_go_down_to_one_texel() {
+        let image = rgba(4, 2, |x, _| [if x < 2 { 0.0 } else { 1.0 }, 0.0, 0.0, 1.0]);
+        let texture = Texture2D::from_image_as(
+            &DynamicImage::ImageRgba32F(image),
+            TextureFormat::Rgba32Float,
+        )
+        .unwrap()
+        .with_mips(MipFilter::Box);
+        let data = texture.data();
+        assert_eq!(data.mip_level_count(), 3);
+        let sizes: Vec<usize> = data.levels().iter().map(|l| l.len()).collect();
+        assert_eq!(sizes, [8 * 16, 2 * 16, 16]);
+        let last = decode_texels(TextureFormat::Rgba32Float, 1, 1, &data.levels()[2]).unwrap();
+        assert_eq!(last.get_pixel(0, 0).0, [0.5, 0.0, 0.0, 1.0]);
+    }
+
+    #[test]
+    fn srgb_mips_are_filtered_in_linear_space() {
+        let image = rgba(2, 1, |x, _| [x as f32, x as f32, x as f32, 1.0]);
+        let data = TextureData::from_images(
+            &[DynamicImage::ImageRgba32F(image)],
+            TextureFormat::Rgba8UnormSrgb,
+        )
+        .unwrap()
+        .with_mips(MipFilter::Box);
+        let expected = (linear_to_srgb(0.5) * 255.0).round() as u8;
+        assert_eq!(data.levels()[1], [expected, expected, expected, 255]);
+    }
+
+    #[test]
+    fn array_layers_get_separate_mips() {
+        let black = DynamicImage::ImageRgba32F(rgba(2, 2, |_, _| [0.0, 0.0, 0.0, 1.0]));
+        let white = DynamicImage::ImageRgba32F(rgba(2, 2, |_, _| [1.0; 4]));
+        let array = Texture2DArray::from_images_as(&[black, white], TextureFormat::Rgba8Unorm)
+            .unwrap()
+            .with_mips(MipFilter::Box);
+        assert_eq!(array.data().levels()[1], [0, 0, 0, 255, 255, 255, 255, 255]);
+    }
+
+    #[test]
+    fn srgb_conversions_invert() {
+        for i in 0..=20 {
+            let x = i as f32 / 20.0;
+            assert!((linear_to_srgb(srgb_to_linear(x)) - x).abs() < 1e-5);
+        }
+    }
+}
@@ -0,0 +1,317 @@
+// The compute path for drawing the view. Instead of the whole ray march running in `fragment`,
+// trace.slang traces the image tile by tile into a storage texture, with one submission per tile,
+// and blit.slang copies the texture into the render-scale target, which ScaledView then upscales
+// like the fragment path's image. Short submissions keep a slow frame from
+// stalling the display, and tile and workgroup sizes can be tuned through `Tuning`.
+// In progressive mode a frame only traces a few tiles, so an image too slow to trace at once
+// builds up over several frames in the persistent texture, partial results shown as it goes.
+
+use std::{collections::HashMap, error::Error};
+
+use naga::valid::Capabilities;
+use wgpu::{
+    BindGroup, BindGroupDescriptor, BindGroupLayout, CommandEncoderDescriptor,
+    ComputePassDescriptor, RenderPipeline, TextureFormat, TextureView,
+};
+
+use crate::{
+    codegen::SlangType,
+    compiler::{ShaderCompiler, ShaderProgram},
+    compute::ComputeProgram,
+    create_render_pipeline,
+    reflection::{
+        BindingResources, Cursor, Writable, bind_group_entries_from_layout, buffers_from_layout,
+    },
+    texture::StorageTexture2D,
+    tuning::Tuning,
+    validation,
+};
+
+pub const TRACE_MODULE: &str = "trace.slang";
+pub const BLIT_MODULE: &str = "blit.slang";
+const TRACE_KERNEL: &str = "trace";
+// `traced` is declared `[format("rgba16f")]` in trace.slang
+const TRACED_FORMAT: TextureFormat = TextureFormat::Rgba16Float;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TraceMode {
+    // Every tile, every frame
+    Full,
+    // `Tuning::trace_tiles_per_frame` tiles per frame until the image is done, then none until
+    // `restart`
+    Progressive,
+}
+
+// Pixel rectangle one dispatch covers
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Writable, SlangType)]
+pub struct Tile {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+// Row by row, clipped at the right and bottom edges
+pub fn tiles(width: u32, height: u32, tile_size: u32) -> Vec<Tile> {
+    let mut out = Vec::new();
+    for y in (0..height).step_by(tile_size as usize) {
+        for x in (0..width).step_by(tile_size as usize) {
+            out.push(Tile {
+                x,
+                y,
+                width: tile_size.min(width - x),
+                height: tile_size.min(height - y),
+            });
+        }
+    }
+    out
+}
+
+pub(crate) fn empty_resources() -> BindingResources {
+    BindingResources {
+        buffers: HashMap::new(),
+        textures: HashMap::new(),
+        texture_views: HashMap::new(),
+        samplers: HashMap::new(),
+    }
+}
+
+pub struct TracedView {
+    compute: ComputeProgram,
+    compute_resources: BindingResources,
+    blit: ShaderProgram,
+    blit_pipeline: RenderPipeline,
+    blit_layouts: HashMap<usize, BindGroupLayout>,
+    blit_resources: BindingResources,
+    output: StorageTexture2D,
+    tile_size: u32,
+    mode: TraceMode,
+    tiles_per_frame: usize,
+    // Progressive only: tiles before this one are done
+    next_tile: usize,
+}
+
+impl TracedView {
+    // `compiler` has to link all entry points, trace.slang and blit.slang name theirs differently
+    pub fn new(
+        device: &wgpu::Device,
+        compiler: &ShaderCompiler,
+        capabilities: Capabilities,
+        surface_format: TextureFormat,
+        tuning: &Tuning,
+        mode: TraceMode,
+        (width, height): (u32, u32),
+    ) -> Result<Self, Box<dyn Error>> {
+        let trace = compiler.compile(TRACE_MODULE)?;
+        validation::validate(&trace, capabilities)?;
+        let blit = compiler.compile(BLIT_MODULE)?;
+        validation::validate(&blit, capabilities)?;
+
+        let constants = tuning.pipeline_constants(&trace);
+        let compute = ComputeProgram::with_constants(device, trace, &constants)?;
+        let mut compute_resources = empty_resources();
+        compute_resources.buffers = buffers_from_layout(device, compute.layout_entries());
+        let (blit_pipeline, blit_layouts) =
+            create_render_pipeline(device, &blit, surface_format, tuning);
+        let mut blit_resources = empty_resources();
+        blit_resources.buffers = buffers_from_layout(device, blit.layout_entries());
+
+        Ok(TracedView {
+            compute,
+            compute_resources,
+            blit,
+            blit_pipeline,
+            blit_layouts,
+            blit_resources,
+            output: StorageTexture2D::new(width, height, TRACED_FORMAT),
+            tile_size: tuning.trace_tile_size.max(1),
+            mode,
+            tiles_per_frame: tuning.trace_tiles_per_frame.max(1) as usize,
+            next_tile: 0,
+        })
+    }
+
+    pub fn mode(&self) -> TraceMode {
+        self.mode
+    }
+
+    // Starts over if the size changed
+    pub fn resize(&mut self, width: u32, height: u32) {
+        if (width, height) != self.output.size() {
+            self.output.resize(width, height);
+            self.restart();
+        }
+    }
+
+    // For when the image changed, the finished tiles get traced again
+    pub fn restart(&mut self) {
+        self.next_tile = 0;
+    }
+
+    pub fn is_complete(&self) -> bool {
+        let (width, height) = self.output.size();
+        self.next_tile >= tiles(width, height, self.tile_size).len()
+    }
+
+    // `globals` fills the parameters trace.slang shares with gorilla.slang
+    pub fn render(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        globals: &impl Writable,
+        target: &TextureView,
+    ) {
+        let top_cursor = Cursor::fresh(self.compute.program().global_type_layout());
+        globals.write_at_cursor(top_cursor, device, queue, &mut self.compute_resources);
+        self.output.write_at_cursor(
+            top_cursor.navigate_field_by_name("traced").unwrap(),
+            device,
+            queue,
+            &mut self.compute_resources,
+        );
+        let tile_cursor = top_cursor
+            .navigate_field_by_name("tile")
+            .and_then(|c| c.navigate_child())
+            .unwrap();
+        let bind_groups = self
+            .compute
+            .create_bind_groups(device, &self.compute_resources);
+
+        let (width, height) = self.output.size();
+        let all_tiles = tiles(width, height, self.tile_size);
+        let pending = match self.mode {
+            TraceMode::Full => &all_tiles[..],
+            TraceMode::Progressive => {
+                let start = self.next_tile.min(all_tiles.len());
+                self.next_tile = (start + self.tiles_per_frame).min(all_tiles.len());
+                &all_tiles[start..self.next_tile]
+            }
+        };
+        // The tile goes in through a queue write, which lands before the next submission only,
+        // so every tile needs its own
+        for &tile in pending {
+            tile.write_at_cursor(tile_cursor, device, queue, &mut self.compute_resources);
+            let mut encoder = device.create_command_encoder(&CommandEncoderDescriptor {
+                label: Some("trace_encoder"),
+            });
+            {
+                let mut compute_pass = encoder.begin_compute_pass(&ComputePassDescriptor {
+                    label: Some("trace_pass"),
+                    timestamp_writes: None,
+                });
+                self.compute.dispatch_for_extent(
+                    &mut compute_pass,
+                    TRACE_KERNEL,
+                    &bind_groups,
+                    [tile.width, tile.height, 1],
+                );
+            }
+            queue.submit(std::iter::once(encoder.finish()));
+        }
+
+        self.blit(device, queue, target);
+    }
+
+    fn blit(&mut self, device: &wgpu::Device, queue: &wgpu::Queue, target: &TextureView) {
+        let top_cursor = Cursor::fresh(self.blit.global_type_layout());
+        self.output.write_at_cursor(
+            top_cursor.navigate_field_by_name("traced").unwrap(),
+            device,
+            queue,
+            &mut self.blit_resources,
+        );
+        let bind_groups: HashMap<usize, BindGroup> =
+            bind_group_entries_from_layout(self.blit.layout_entries(), &self.blit_resources)
+                .iter()
+                .map(|(&set, entries)| {
+                    (
+                        set,
+                        device.create_bind_group(&BindGroupDescriptor {
+                            label: Some(&format!("blit_bg{}", set)),
+                            layout: &self.blit_layouts[&set],
+                            entries,
+                        }),
+                    )
+                })
+                .collect();
+
+        let mut encoder = device.create_command_encoder(&CommandEncoderDescriptor {
+            label: Some("blit_encoder"),
+        });
+        {
+            let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("blit_pass"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: target,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                        store: wgpu::StoreOp::Store,
+                    },
+                })],
+                depth_stencil_attachment: None,
+                timestamp_writes: None,
+                occlusion_query_set: None,
+            });
+            render_pass.set_pipeline(&self.blit_pipeline);
+            for (&set, bind_group) in &bind_groups {
+                render_pass.set_bind_group(set as u32, bind_group, &[]);
+            }
+            render_pass.draw(0..3, 0..1);
+        }
+        queue.submit(std::iter::once(encoder.finish()));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tiles_cover_the_image_once() {
+        let all = tiles(20, 12, 8);
+        assert_eq!(all.len(), 6);
+        let mut covered = vec![0; 20 * 12];
+        for tile in &all {
+            for y in tile.y..tile.y + tile.height {
+                for x in tile.x..tile.x + tile.width {
+                    covered[(y * 20 + x) as usize] += 1;
+                }
+            }
+        }
+        assert!(covered.iter().all(|&n| n == 1));
+    }
+
+    #[test]
+    fn edge_tiles_are_clipped() {
+        assert_eq!(
+            tiles(10, 5, 8),
+            [
+                Tile {
+                    x: 0,
+                    y: 0,
+                    width: 8,
+                    height: 5,
+                },
+                Tile {
+                    x: 8,
+                    y: 0,
+                    width: 2,
+                    height: 5,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn tiles_go_row_by_row() {
+        let starts: Vec<_> = tiles(16, 16, 8).iter().map(|t| (t.x, t.y)).collect();
+        assert_eq!(starts, [(0, 0), (8, 0), (0, 8), (8, 8)]);
+    }
+
+    #[test]
+    fn empty_image_has_no_tiles() {
+        assert!(tiles(0, 0, 8).is_empty());
+        assert!(tiles(0, 16, 8).is_empty());
+    }
+}
@@ -0,0 +1,126 @@
+// Tunables of the ray stepping in shading.slang and of the compute path in trace.slang.
+// The projection step count is unrolled and the workgroup size is fixed by `[numthreads]`, so
+// those go in as preprocessor defines and changing them means recompiling. The stepping lengths
+// are specialization constants, which Slang emits as WGSL `override`s (SPIR-V spec constants), so
+// they only need a new pipeline. The tile size is only used on the Rust side.
+
+use std::collections::HashMap;
+
+use crate::{
+    compiler::{ShaderCompiler, ShaderProgram},
+    validation,
+};
+
+// Ids of the `[vk::constant_id]` declarations in tuning.slang
+const OUTER_LENGTH_ID: u32 = 0;
+const OUTER_ITERATIONS_ID: u32 = 1;
+const THROAT_DT_ID: u32 = 2;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Tuning {
+    // KSTEPS, Newton steps when projecting onto the surface
+    pub projection_steps: u32,
+    pub outer_length: f32,
+    pub outer_iterations: i32,
+    pub throat_dt: f32,
+    // Compute path only: TRACE_GROUP_SIZE, the side of a square workgroup in pixels
+    pub trace_group_size: u32,
+    // Compute path only: side of the square tile traced per submission, in pixels
+    pub trace_tile_size: u32,
+    // Progressive compute path only: tiles traced per frame
+    pub trace_tiles_per_frame: u32,
+}
+
+// Same as the defaults in the shader
+impl Default for Tuning {
+    fn default() -> Self {
+        Tuning {
+            projection_steps: 10,
+            outer_length: 0.1,
+            outer_iterations: 300,
+            throat_dt: 0.03,
+            trace_group_size: 8,
+            trace_tile_size: 256,
+            trace_tiles_per_frame: 4,
+        }
+    }
+}
+
+impl Tuning {
+    pub fn apply_defines(&self, compiler: ShaderCompiler) -> ShaderCompiler {
+        compiler
+            .define("KSTEPS", &self.projection_steps.to_string())
+            .define("TRACE_GROUP_SIZE", &self.trace_group_size.to_string())
+    }
+
+    // For `PipelineCompilationOptions::constants`. wgpu rejects constants the shader doesn't
+    // declare, so `program` only gets the ones it does, none if it doesn't import tuning.slang.
+    pub fn pipeline_constants(&self, program: &ShaderProgram) -> HashMap<String, f64> {
+        match validation::parse_module(program) {
+            Ok(module) => declared_constants(self.all_constants(), &module),
+            // Validation reports it, before any pipeline is made
+            Err(_) => HashMap::new(),
+        }
+    }
+
+    fn all_constants(&self) -> HashMap<String, f64> {
+        HashMap::from([
+            (OUTER_LENGTH_ID.to_string(), self.outer_length as f64),
+            (
+                OUTER_ITERATIONS_ID.to_string(),
+                self.outer_iterations as f64,
+            ),
+            (THROAT_DT_ID.to_string(), self.throat_dt as f64),
+        ])
+    }
+}
+
+// The constants `module` has an override for, keyed by id or by name like wgpu does
+fn declared_constants(
+    constants: HashMap<String, f64>,
+    module: &naga::Module,
+) -> HashMap<String, f64> {
+    constants
+        .into_iter()
+        .filter(|(key, _)| {
+            module.overrides.iter().any(|(_, o)| {
+                o.id.is_some_and(|id| id.to_string() == *key) || o.name.as_deref() == Some(key)
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn constants(source: &str) -> HashMap<String, f64> {
+        let module = naga::front::wgsl::parse_str(source).unwrap();
+        declared_constants(Tuning::default().all_constants(), &module)
+    }
+
+    #[test]
+    fn keeps_declared_ids() {
+        let found = constants(
+            "@id(0) override outer_length: f32 = 0.1;\n\
+             @id(2) override throat_dt: f32 = 0.03;\n",
+        );
+        let mut keys: Vec<_> = found.keys().map(String::as_str).collect();
+        keys.sort();
+        assert_eq!(keys, ["0", "2"]);
+        assert_eq!(found["0"], Tuning::default().outer_length as f64);
+    }
+
+    #[test]
+    fn programs_without_overrides_get_none() {
+        assert!(
+            constants("@fragment fn main() -> @location(0) vec4<f32> { return vec4(1.0); }")
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn other_overrides_do_not_match() {
+        assert!(constants("@id(7) override scale: f32 = 1.0;").is_empty());
+    }
+}
@@ -0,0 +1,337 @@
+// Runs naga over the code Slang emitted, before wgpu sees it. wgpu panics on invalid
+// modules, while this gives an error we can print and recover from.
+// Errors in WGSL are traced back to the Slang function they were generated from, and to the
+// Slang source line when the emitted code carries `#line` directives.
+
+use std::{error::Error, fmt};
+
+use naga::{
+    Span,
+    valid::{Capabilities, ModuleInfo, ValidationFlags, Validator},
+};
+
+use wgpu::{DownlevelFlags, Features};
+
+use crate::compiler::{ShaderBackend, ShaderProgram};
+
+// Where a span of emitted WGSL came from
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SlangOrigin {
+    // With the `_0`-style suffix Slang appends to emitted names removed
+    pub function: Option<String>,
+    pub file: Option<String>,
+    pub line: Option<u32>,
+}
+
+impl fmt::Display for SlangOrigin {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &self.function {
+            Some(function) => write!(f, "in Slang function `{}`", function)?,
+            None => write!(f, "outside any function")?,
+        }
+        match (&self.file, self.line) {
+            (Some(file), Some(line)) => write!(f, " ({}:{})", file, line),
+            (None, Some(line)) => write!(f, " (line {})", line),
+            _ => Ok(()),
+        }
+    }
+}
+
+// Slang writes `#line 12 "gorilla.slang"`, or `#line 12` to stay in the same file.
+// Targets without a preprocessor may get it behind `//`.
+fn parse_line_directive(line: &str) -> Option<(u32, Option<String>)> {
+    let line = line.trim_start();
+    let line = line.strip_prefix("//").map_or(line, str::trim_start);
+    let mut rest = line.strip_prefix("#line")?.split_whitespace();
+    let number = rest.next()?.parse().ok()?;
+    let file = rest.next().map(|f| f.trim_matches('"').to_string());
+    Some((number, file))
+}
+
+// `fn horse_steppin_0(` at the start of a line opens a function, a `}` there closes it
+fn parse_function_start(line: &str) -> Option<&str> {
+    let name = line.strip_prefix("fn ")?.split('(').next()?.trim();
+    Some(match name.rsplit_once('_') {
+        Some((base, suffix))
+            if !suffix.is_empty() && suffix.chars().all(|c| c.is_ascii_digit()) =>
+        {
+            base
+        }
+        _ => name,
+    })
+}
+
+pub fn slang_origin(code: &str, offset: usize) -> SlangOrigin {
+    let mut origin = SlangOrigin {
+        function: None,
+        file: None,
+        line: None,
+    };
+    let mut line_start = 0;
+    for line in code.split_inclusive('\n') {
+        if line_start > offset {
+            break;
+        }
+        line_start += line.len();
+        if let Some((number, file)) = parse_line_directive(line) {
+            // The directive names the line after it
+            origin.line = Some(number);
+            if file.is_some() {
+                origin.file = file;
+            }
+            continue;
+        }
+        if let Some(function) = parse_function_start(line) {
+            origin.function = Some(function.to_string());
+        } else if line.starts_with('}') {
+            origin.function = None;
+        }
+        if line_start <= offset {
+            origin.line = origin.line.map(|l| l + 1);
+        }
+    }
+    origin
+}
+
+fn origins(code: &str, spans: impl Iterator<Item = Span>) -> Vec<SlangOrigin> {
+    let mut origins: Vec<SlangOrigin> = Vec::new();
+    for span in spans {
+        if let Some(range) = span.to_range() {
+            let origin = slang_origin(code, range.start);
+            if !origins.contains(&origin) {
+                origins.push(origin);
+            }
+        }
+    }
+    origins
+}
+
+#[derive(Debug)]
+pub enum ValidationError {
+    // naga couldn't read the emitted code at all
+    Parse {
+        message: String,
+        origins: Vec<SlangOrigin>,
+    },
+    Validation {
+        message: String,
+        origins: Vec<SlangOrigin>,
+    },
+}
+
+impl ValidationError {
+    // Empty for SPIR-V, which has no text to point into
+    pub fn origins(&self) -> &[SlangOrigin] {
+        match self {
+            ValidationError::Parse { origins, .. }
+            | ValidationError::Validation { origins, .. } => origins,
+        }
+    }
+}
+
+impl fmt::Display for ValidationError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let (header, message, origins) = match self {
+            ValidationError::Parse { message, origins } => {
+                ("naga could not parse the shader", message, origins)
+            }
+            ValidationError::Validation { message, origins } => {
+                ("shader failed validation", message, origins)
+            }
+        };
+        write!(f, "{}", header)?;
+        for origin in origins {
+            write!(f, "\n  {}", origin)?;
+        }
+        write!(f, "\n{}", message)
+    }
+}
+
+impl Error for ValidationError {}
+
+pub fn parse_module(program: &ShaderProgram) -> Result<naga::Module, ValidationError> {
+    match program.backend() {
+        ShaderBackend::Wgsl => {
+            let source = program.code_str().unwrap_or("");
+            naga::front::wgsl::parse_str(source).map_err(|e| ValidationError::Parse {
+                message: e.emit_to_string(source),
+                origins: origins(source, e.labels().map(|(span, _)| span)),
+            })
+        }
+        ShaderBackend::SpirV => {
+            naga::front::spv::parse_u8_slice(program.code(), &naga::front::spv::Options::default())
+                .map_err(|e| ValidationError::Parse {
+                    message: e.to_string(),
+                    origins: Vec::new(),
+                })
+        }
+    }
+}
+
+// What naga may accept on a device with these features. The same mapping wgpu makes before
+// validating a module itself, so code that passes here doesn't fail at pipeline creation.
+pub fn capabilities(features: Features, downlevel: DownlevelFlags) -> Capabilities {
+    let mut caps = Capabilities::empty();
+    caps.set(
+        Capabilities::PUSH_CONSTANT,
+        features.contains(Features::PUSH_CONSTANTS),
+    );
+    caps.set(
+        Capabilities::FLOAT64,
+        features.contains(Features::SHADER_F64),
+    );
+    caps.set(
+        Capabilities::PRIMITIVE_INDEX,
+        features.contains(Features::SHADER_PRIMITIVE_INDEX),
+    );
+    caps.set(
+        Capabilities::SAMPLED_TEXTURE_AND_STORAGE_BUFFER_ARRAY_NON_UNIFORM_INDEXING
+            | Capabilities::SAMPLER_NON_UNIFORM_INDEXING,
+        features.contains(Features::SAMPLED_TEXTURE_AND_STORAGE_BUFFER_ARRAY_NON_UNIFORM_INDEXING),
+    );
+    caps.set(
+        Capabilities::UNIFORM_BUFFER_AND_STORAGE_TEXTURE_ARRAY_NON_UNIFORM_INDEXING,
+        features.contains(Features::UNIFORM_BUFFER_AND_STORAGE_TEXTURE_ARRAY_NON_UNIFORM_INDEXING),
+    );
+    caps.set(
+        Capabilities::STORAGE_TEXTURE_16BIT_NORM_FORMATS,
+        features.contains(Features::TEXTURE_FORMAT_16BIT_NORM),
+    );
+    caps.set(
+        Capabilities::MULTIVIEW,
+        features.contains(Features::MULTIVIEW),
+    );
+    caps.set(
+        Capabilities::EARLY_DEPTH_TEST,
+        features.contains(Features::SHADER_EARLY_DEPTH_TEST),
+    );
+    caps.set(
+        Capabilities::SHADER_INT64,
+        features.contains(Features::SHADER_INT64),
+    );
+    caps.set(
+        Capabilities::DUAL_SOURCE_BLENDING,
+        features.contains(Features::DUAL_SOURCE_BLENDING),
+    );
+    caps.set(
+        Capabilities::MULTISAMPLED_SHADING,
+        downlevel.contains(DownlevelFlags::MULTISAMPLED_SHADING),
+    );
+    caps.set(
+        Capabilities::CUBE_ARRAY_TEXTURES,
+        downlevel.contains(DownlevelFlags::CUBE_ARRAY_TEXTURES),
+    );
+    caps
+}
+
+pub fn validate(
+    program: &ShaderProgram,
+    capabilities: Capabilities,
+) -> Result<(naga::Module, ModuleInfo), ValidationError> {
+    let module = parse_module(program)?;
+    let info = Validator::new(ValidationFlags::all(), capabilities)
+        .validate(&module)
+        .map_err(|e| match program.backend() {
+            ShaderBackend::Wgsl => {
+                let source = program.code_str().unwrap_or("");
+                ValidationError::Validation {
+                    message: e.emit_to_string(source),
+                    origins: origins(source, e.spans().map(|(span, _)| *span)),
+                }
+            }
+            ShaderBackend::SpirV => ValidationError::Validation {
+                message: e.to_string(),
+                origins: Vec::new(),
+            },
+        })?;
+    Ok((module, info))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CODE: &str = "struct Camera_0 {\n\
+                        #line 10 \"gorilla.slang\"\n\
+                        fn fragpos_to_ray_0(pos: vec2<f32>) -> f32 {\n\
+                        \x20   let x = pos.x;\n\
+                        \x20   return x;\n\
+                        }\n\
+                        #line 40\n\
+                        fn shade_1() {\n\
+                        \x20   let a = 1;\n\
+                        }\n\
+                        const after = 0;\n";
+
+    fn origin_of(needle: &str) -> SlangOrigin {
+        slang_origin(CODE, CODE.find(needle).unwrap())
+    }
+
+    #[test]
+    fn line_directives() {
+        assert_eq!(
+            parse_line_directive("#line 12 \"gorilla.slang\""),
+            Some((12, Some("gorilla.slang".to_string())))
+        );
+        assert_eq!(parse_line_directive("  #line 12"), Some((12, None)));
+        assert_eq!(parse_line_directive("// #line 7 \"a.slang\"").unwrap().0, 7);
+        assert_eq!(parse_line_directive("#line x"), None);
+        assert_eq!(parse_line_directive("let line = 3;"), None);
+    }
+
+    #[test]
+    fn mangling_suffix_is_stripped() {
+        assert_eq!(
+            parse_function_start("fn horse_steppin_0(x: f32) {"),
+            Some("horse_steppin")
+        );
+        assert_eq!(parse_function_start("fn shade_12() {"), Some("shade"));
+        assert_eq!(parse_function_start("fn main() {"), Some("main"));
+        assert_eq!(
+            parse_function_start("fn base_and_delta(x: f32) {"),
+            Some("base_and_delta")
+        );
+        assert_eq!(
+            parse_function_start("fn trailing_(x: f32) {"),
+            Some("trailing_")
+        );
+        assert_eq!(parse_function_start("    fn nested_0() {"), None);
+    }
+
+    #[test]
+    fn lines_count_on_from_a_directive() {
+        let origin = origin_of("let x");
+        assert_eq!(origin.function.as_deref(), Some("fragpos_to_ray"));
+        assert_eq!(origin.file.as_deref(), Some("gorilla.slang"));
+        assert_eq!(origin.line, Some(11));
+        assert_eq!(origin_of("return x").line, Some(12));
+    }
+
+    // A bare `#line` resets the line and keeps the file
+    #[test]
+    fn bare_directive_keeps_the_file() {
+        let origin = origin_of("let a");
+        assert_eq!(origin.function.as_deref(), Some("shade"));
+        assert_eq!(origin.file.as_deref(), Some("gorilla.slang"));
+        assert_eq!(origin.line, Some(41));
+    }
+
+    #[test]
+    fn closing_brace_leaves_the_function() {
+        let origin = origin_of("const after");
+        assert_eq!(origin.function, None);
+        assert_eq!(origin.line, Some(43));
+    }
+
+    #[test]
+    fn spans_before_any_directive_have_no_line() {
+        assert_eq!(
+            slang_origin(CODE, 0),
+            SlangOrigin {
+                function: None,
+                file: None,
+                line: None,
+            }
+        );
+    }
+}